@@ -1,5 +1,9 @@
 use crate::r#enum::compression::Compression;
+use crate::r#enum::duplicate::{Dedupe, DedupePolicy};
+use crate::r#enum::report::ReportFormat;
+use crate::r#enum::walk::{NonUtf8Names, Symlinks};
 use clap::{Parser, Subcommand};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -11,11 +15,23 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
+    /// Generates a CA & a server certificate to serve files over TLS with client certificates
+    Certgen(CertgenCommand),
+
     /// Generates an Index for files hosted on Google Drive
     Gdrive(GoogleDriveCommand),
 
     /// Generates an Index for files hosted via a http server
     Http(HttpCommand),
+
+    /// Decodes an existing Index file and prints its content
+    Inspect(InspectCommand),
+
+    /// Generates a RSA key pair to encrypt Index files with
+    Keygen(KeygenCommand),
+
+    /// Scans local folders like http & serves the Index and the files itself
+    Serve(ServeCommand),
 }
 
 pub trait IndexCommand {
@@ -34,6 +50,27 @@ pub trait IndexCommand {
     fn theme_blacklist(&self) -> Option<Vec<String>>;
     fn theme_whitelist(&self) -> Option<Vec<String>>;
     fn theme_error(&self) -> Option<String>;
+    fn location_path(&self) -> Option<PathBuf>;
+    fn one_fichier_keys(&self) -> Option<Vec<String>>;
+    fn client_cert(&self) -> Option<PathBuf>;
+    fn client_key(&self) -> Option<PathBuf>;
+    fn index_per_folder(&self) -> bool;
+    fn users(&self) -> Option<PathBuf>;
+    fn users_output_path(&self) -> &PathBuf;
+    fn keep_latest_updates(&self) -> Option<usize>;
+    fn dedupe(&self) -> Option<Vec<Dedupe>>;
+    fn dedupe_policy(&self) -> DedupePolicy;
+    fn dedupe_report(&self) -> Option<PathBuf>;
+    fn include(&self) -> Vec<String>;
+    fn exclude(&self) -> Vec<String>;
+    fn include_path(&self) -> Vec<String>;
+    fn exclude_path(&self) -> Vec<String>;
+    fn min_size(&self) -> Option<i64>;
+    fn max_size(&self) -> Option<i64>;
+    fn filter_report(&self) -> Option<PathBuf>;
+    fn filter_report_format(&self) -> Option<ReportFormat>;
+    fn title_allowlist(&self) -> Option<PathBuf>;
+    fn title_blocklist(&self) -> Option<PathBuf>;
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -49,6 +86,10 @@ pub struct GoogleDriveCommand {
     #[arg(long)]
     pub no_recursion: bool,
 
+    /// Ignores .rustfoilignore files in scanned folders
+    #[arg(long)]
+    pub no_ignore_files: bool,
+
     /// Adds files without valid Title ID
     #[arg(long)]
     pub add_nsw_files_without_title_id: bool,
@@ -57,6 +98,67 @@ pub struct GoogleDriveCommand {
     #[arg(long)]
     pub add_non_nsw_files: bool,
 
+    /// Only adds files whose name matches this regex, can be repeated
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skips files whose name matches this regex, can be repeated
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Only adds files whose path relative to the scanned folder matches this glob, can be repeated
+    #[arg(long)]
+    pub include_path: Vec<String>,
+
+    /// Skips files whose path relative to the scanned folder matches this glob, can be repeated
+    #[arg(long)]
+    pub exclude_path: Vec<String>,
+
+    /// Skips files smaller than this size in bytes
+    #[arg(long)]
+    pub min_size: Option<i64>,
+
+    /// Skips files larger than this size in bytes
+    #[arg(long)]
+    pub max_size: Option<i64>,
+
+    /// Path to a text or JSON file of Title IDs to only add, `<base title id>*` also matches its updates & DLCs
+    #[arg(long)]
+    pub title_allowlist: Option<PathBuf>,
+
+    /// Path to a text or JSON file of Title IDs to skip, `<base title id>*` also matches its updates & DLCs
+    #[arg(long)]
+    pub title_blocklist: Option<PathBuf>,
+
+    /// Keeps only the newest update per title, or the N newest if a number is given
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "1",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub keep_latest_updates: Option<usize>,
+
+    /// Removes duplicate files detected by the given keys, applied in order
+    #[arg(long, value_enum)]
+    pub dedupe: Option<Vec<Dedupe>>,
+
+    /// Which copy of a duplicate to keep
+    #[arg(long, value_enum, default_value = "prefer-first")]
+    pub dedupe_policy: DedupePolicy,
+
+    /// Path to write a JSON report of removed duplicates to
+    #[arg(long)]
+    pub dedupe_report: Option<PathBuf>,
+
+    /// Path to write a report of every excluded file & the reason why to
+    #[arg(long)]
+    pub filter_report: Option<PathBuf>,
+
+    /// Format of the filter report, defaults to json for .json paths & table otherwise
+    #[arg(long, value_enum)]
+    pub filter_report_format: Option<ReportFormat>,
+
     /// Compression to use for index file
     #[arg(short, long, value_enum)]
     pub compression: Option<Compression>,
@@ -101,6 +203,34 @@ pub struct GoogleDriveCommand {
     #[arg(long)]
     pub theme_error: Option<String>,
 
+    /// Path to location.json file containing locations to add to the index
+    #[arg(long)]
+    pub location_path: Option<PathBuf>,
+
+    /// Adds 1Fichier API keys to be used with all 1f:/ requests
+    #[arg(long)]
+    pub one_fichier_keys: Option<Vec<String>>,
+
+    /// Path to PEM encoded client certificate Tinfoil should present
+    #[arg(long, requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// Path to PEM encoded private key of the client certificate
+    #[arg(long, requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// Writes one Index per scanned folder next to the output path, uploads it into that folder & lists the uploaded Indexes as directories in the root Index
+    #[arg(long)]
+    pub index_per_folder: bool,
+
+    /// Path to a TOML users file, additionally writes one Index per user (serve requires them to authenticate)
+    #[arg(long)]
+    pub users: Option<PathBuf>,
+
+    /// Folder the Indexes of the users are written to as <username>.tfl
+    #[arg(long, default_value = "out")]
+    pub users_output_path: PathBuf,
+
     /// Path to Google Application Credentials
     #[arg(long, default_value = "credentials.json")]
     pub credentials: PathBuf,
@@ -148,8 +278,13 @@ pub struct HttpCommand {
     pub http_base_url: String,
 
     /// local folder paths to scan
+    #[arg(required_unless_present = "crawl")]
     pub folder_paths: Vec<PathBuf>,
 
+    /// Crawls the directory listing at the base Url (nginx, Apache or lighttpd) instead of scanning local folders
+    #[arg(long, conflicts_with = "folder_paths")]
+    pub crawl: bool,
+
     /// Path to output index file
     #[arg(short = 'o', long, default_value = "index.tfl")]
     pub output_path: PathBuf,
@@ -158,6 +293,58 @@ pub struct HttpCommand {
     #[arg(long)]
     pub no_recursion: bool,
 
+    /// Ignores .rustfoilignore files in scanned folders
+    #[arg(long)]
+    pub no_ignore_files: bool,
+
+    /// Scans at most this many subdirectory levels deep for each Folder entered
+    #[arg(long, conflicts_with = "no_recursion")]
+    pub max_depth: Option<usize>,
+
+    /// Only scans these subdirectories of each Folder entered, can be repeated
+    #[arg(long)]
+    pub include_dir: Vec<String>,
+
+    /// Skips subdirectories with this name at any depth, can be repeated
+    #[arg(long)]
+    pub exclude_dir: Vec<String>,
+
+    /// How symlinks found while scanning are handled
+    #[arg(long, value_enum, default_value = "follow")]
+    pub symlinks: Symlinks,
+
+    /// Scans files & folders starting with a `.`
+    #[arg(long)]
+    pub include_hidden: bool,
+
+    /// Scans partially downloaded files (.part, .!qB, .crdownload)
+    #[arg(long)]
+    pub include_partial: bool,
+
+    /// How file names which aren't valid UTF-8 are handled
+    #[arg(long, value_enum, default_value = "lossy")]
+    pub non_utf8_names: NonUtf8Names,
+
+    /// How many folders are read at once while scanning, or how many sizes are requested at once while crawling
+    #[arg(long, default_value = "16")]
+    pub scan_workers: usize,
+
+    /// Reads the Title ID from the NSP/NSZ/XCI/XCZ header of files without one in their name
+    #[arg(long)]
+    pub inspect_containers: bool,
+
+    /// Compares the size declared by NSP/NSZ/XCI/XCZ headers with the real file size
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Path to write a JSON report of all verified files to, implies `--verify`
+    #[arg(long)]
+    pub verify_report: Option<PathBuf>,
+
+    /// Excludes truncated, padded & corrupt files from the index, implies `--verify`
+    #[arg(long)]
+    pub exclude_invalid: bool,
+
     /// Adds files without valid Title ID
     #[arg(long)]
     pub add_nsw_files_without_title_id: bool,
@@ -166,6 +353,67 @@ pub struct HttpCommand {
     #[arg(long)]
     pub add_non_nsw_files: bool,
 
+    /// Only adds files whose name matches this regex, can be repeated
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skips files whose name matches this regex, can be repeated
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Only adds files whose path relative to the scanned folder matches this glob, can be repeated
+    #[arg(long)]
+    pub include_path: Vec<String>,
+
+    /// Skips files whose path relative to the scanned folder matches this glob, can be repeated
+    #[arg(long)]
+    pub exclude_path: Vec<String>,
+
+    /// Skips files smaller than this size in bytes
+    #[arg(long)]
+    pub min_size: Option<i64>,
+
+    /// Skips files larger than this size in bytes
+    #[arg(long)]
+    pub max_size: Option<i64>,
+
+    /// Path to a text or JSON file of Title IDs to only add, `<base title id>*` also matches its updates & DLCs
+    #[arg(long)]
+    pub title_allowlist: Option<PathBuf>,
+
+    /// Path to a text or JSON file of Title IDs to skip, `<base title id>*` also matches its updates & DLCs
+    #[arg(long)]
+    pub title_blocklist: Option<PathBuf>,
+
+    /// Keeps only the newest update per title, or the N newest if a number is given
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "1",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub keep_latest_updates: Option<usize>,
+
+    /// Removes duplicate files detected by the given keys, applied in order
+    #[arg(long, value_enum)]
+    pub dedupe: Option<Vec<Dedupe>>,
+
+    /// Which copy of a duplicate to keep
+    #[arg(long, value_enum, default_value = "prefer-first")]
+    pub dedupe_policy: DedupePolicy,
+
+    /// Path to write a JSON report of removed duplicates to
+    #[arg(long)]
+    pub dedupe_report: Option<PathBuf>,
+
+    /// Path to write a report of every excluded file & the reason why to
+    #[arg(long)]
+    pub filter_report: Option<PathBuf>,
+
+    /// Format of the filter report, defaults to json for .json paths & table otherwise
+    #[arg(long, value_enum)]
+    pub filter_report_format: Option<ReportFormat>,
+
     /// Compression to use for index file
     #[arg(short, long, value_enum)]
     pub compression: Option<Compression>,
@@ -205,6 +453,104 @@ pub struct HttpCommand {
     /// Adds a custom theme error message to the index
     #[arg(long)]
     pub theme_error: Option<String>,
+
+    /// Path to location.json file containing locations to add to the index
+    #[arg(long)]
+    pub location_path: Option<PathBuf>,
+
+    /// Adds 1Fichier API keys to be used with all 1f:/ requests
+    #[arg(long)]
+    pub one_fichier_keys: Option<Vec<String>>,
+
+    /// Path to PEM encoded client certificate Tinfoil should present
+    #[arg(long, requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// Path to PEM encoded private key of the client certificate
+    #[arg(long, requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// Writes one Index per scanned folder next to the output path & lists them as directories in the root Index, the output folder has to be served at the base Url
+    #[arg(long)]
+    pub index_per_folder: bool,
+
+    /// Path to a TOML users file, additionally writes one Index per user (serve requires them to authenticate)
+    #[arg(long)]
+    pub users: Option<PathBuf>,
+
+    /// Folder the Indexes of the users are written to as <username>.tfl
+    #[arg(long, default_value = "out")]
+    pub users_output_path: PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct InspectCommand {
+    /// Path to index file to inspect
+    pub index_path: PathBuf,
+
+    /// Prints the decoded index as JSON instead of a summary
+    #[arg(long)]
+    pub json: bool,
+
+    /// Path to RSA Private Key (PKCS#8 or PKCS#1 PEM) to decrypt an encrypted index with
+    #[arg(long)]
+    pub private_key: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct KeygenCommand {
+    /// Path to write the RSA Public Key (SPKI PEM) to
+    #[arg(long, default_value = "public.key")]
+    pub public_key: PathBuf,
+
+    /// Path to write the RSA Private Key (PKCS#8 PEM) to
+    #[arg(long, default_value = "private.key")]
+    pub private_key: PathBuf,
+
+    /// Overwrites existing key files
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ServeCommand {
+    #[command(flatten)]
+    pub http: HttpCommand,
+
+    /// Address to listen on, the http base url has to point to it
+    #[arg(long, default_value = "0.0.0.0:8000")]
+    pub bind: SocketAddr,
+
+    /// Path to the PEM encoded certificate (chain) to serve over https with
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Path to the PEM encoded private key of the server certificate
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Path to a PEM encoded CA, files are only served to clients presenting a certificate signed by it
+    #[arg(long, requires = "tls_cert")]
+    pub client_ca: Option<PathBuf>,
+
+    /// Path to the private key of the client CA, issues a client certificate on start & embeds it in the Index (requires --users, the Index would hand it to anyone otherwise)
+    #[arg(long, requires_all = ["client_ca", "users"], conflicts_with = "client_cert")]
+    pub client_ca_key: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CertgenCommand {
+    /// Host name or IP address the server certificate is valid for, can be given multiple times
+    #[arg(long = "hostname", required = true)]
+    pub hostnames: Vec<String>,
+
+    /// Folder to write ca.pem, ca.key, server.pem & server.key to
+    #[arg(short = 'o', long, default_value = ".")]
+    pub output_path: PathBuf,
+
+    /// Overwrites existing certificate files
+    #[arg(long)]
+    pub force: bool,
 }
 
 impl IndexCommand for GoogleDriveCommand {
@@ -267,6 +613,90 @@ impl IndexCommand for GoogleDriveCommand {
     fn theme_error(&self) -> Option<String> {
         self.theme_error.clone()
     }
+
+    fn location_path(&self) -> Option<PathBuf> {
+        self.location_path.clone()
+    }
+
+    fn one_fichier_keys(&self) -> Option<Vec<String>> {
+        self.one_fichier_keys.clone()
+    }
+
+    fn client_cert(&self) -> Option<PathBuf> {
+        self.client_cert.clone()
+    }
+
+    fn client_key(&self) -> Option<PathBuf> {
+        self.client_key.clone()
+    }
+
+    fn index_per_folder(&self) -> bool {
+        self.index_per_folder
+    }
+
+    fn users(&self) -> Option<PathBuf> {
+        self.users.clone()
+    }
+
+    fn users_output_path(&self) -> &PathBuf {
+        &self.users_output_path
+    }
+
+    fn keep_latest_updates(&self) -> Option<usize> {
+        self.keep_latest_updates
+    }
+
+    fn dedupe(&self) -> Option<Vec<Dedupe>> {
+        self.dedupe.clone()
+    }
+
+    fn dedupe_policy(&self) -> DedupePolicy {
+        self.dedupe_policy
+    }
+
+    fn dedupe_report(&self) -> Option<PathBuf> {
+        self.dedupe_report.clone()
+    }
+
+    fn include(&self) -> Vec<String> {
+        self.include.clone()
+    }
+
+    fn exclude(&self) -> Vec<String> {
+        self.exclude.clone()
+    }
+
+    fn include_path(&self) -> Vec<String> {
+        self.include_path.clone()
+    }
+
+    fn exclude_path(&self) -> Vec<String> {
+        self.exclude_path.clone()
+    }
+
+    fn min_size(&self) -> Option<i64> {
+        self.min_size
+    }
+
+    fn max_size(&self) -> Option<i64> {
+        self.max_size
+    }
+
+    fn filter_report(&self) -> Option<PathBuf> {
+        self.filter_report.clone()
+    }
+
+    fn filter_report_format(&self) -> Option<ReportFormat> {
+        self.filter_report_format
+    }
+
+    fn title_allowlist(&self) -> Option<PathBuf> {
+        self.title_allowlist.clone()
+    }
+
+    fn title_blocklist(&self) -> Option<PathBuf> {
+        self.title_blocklist.clone()
+    }
 }
 
 impl IndexCommand for HttpCommand {
@@ -329,4 +759,88 @@ impl IndexCommand for HttpCommand {
     fn theme_error(&self) -> Option<String> {
         self.theme_error.clone()
     }
+
+    fn location_path(&self) -> Option<PathBuf> {
+        self.location_path.clone()
+    }
+
+    fn one_fichier_keys(&self) -> Option<Vec<String>> {
+        self.one_fichier_keys.clone()
+    }
+
+    fn client_cert(&self) -> Option<PathBuf> {
+        self.client_cert.clone()
+    }
+
+    fn client_key(&self) -> Option<PathBuf> {
+        self.client_key.clone()
+    }
+
+    fn index_per_folder(&self) -> bool {
+        self.index_per_folder
+    }
+
+    fn users(&self) -> Option<PathBuf> {
+        self.users.clone()
+    }
+
+    fn users_output_path(&self) -> &PathBuf {
+        &self.users_output_path
+    }
+
+    fn keep_latest_updates(&self) -> Option<usize> {
+        self.keep_latest_updates
+    }
+
+    fn dedupe(&self) -> Option<Vec<Dedupe>> {
+        self.dedupe.clone()
+    }
+
+    fn dedupe_policy(&self) -> DedupePolicy {
+        self.dedupe_policy
+    }
+
+    fn dedupe_report(&self) -> Option<PathBuf> {
+        self.dedupe_report.clone()
+    }
+
+    fn include(&self) -> Vec<String> {
+        self.include.clone()
+    }
+
+    fn exclude(&self) -> Vec<String> {
+        self.exclude.clone()
+    }
+
+    fn include_path(&self) -> Vec<String> {
+        self.include_path.clone()
+    }
+
+    fn exclude_path(&self) -> Vec<String> {
+        self.exclude_path.clone()
+    }
+
+    fn min_size(&self) -> Option<i64> {
+        self.min_size
+    }
+
+    fn max_size(&self) -> Option<i64> {
+        self.max_size
+    }
+
+    fn filter_report(&self) -> Option<PathBuf> {
+        self.filter_report.clone()
+    }
+
+    fn filter_report_format(&self) -> Option<ReportFormat> {
+        self.filter_report_format
+    }
+
+    fn title_allowlist(&self) -> Option<PathBuf> {
+        self.title_allowlist.clone()
+    }
+
+    fn title_blocklist(&self) -> Option<PathBuf> {
+        self.title_blocklist.clone()
+    }
 }
@@ -0,0 +1,45 @@
+use clap::ValueEnum;
+use rustfoil_lib::filter::duplicate::{DuplicateKey, DuplicatePolicy};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Dedupe {
+    /// Same Title ID & version
+    TitleVersion,
+    /// Same file name & size
+    NameSize,
+    /// Same checksum (Google Drive md5Checksum)
+    Checksum,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DedupePolicy {
+    /// Keeps the copy from the first scanned source
+    #[value(name = "prefer-first")]
+    First,
+    /// Keeps the smallest copy
+    #[value(name = "prefer-smaller")]
+    Smaller,
+    /// Keeps NSZ/XCZ copies over NSP/XCI
+    #[value(name = "prefer-nsz")]
+    Nsz,
+}
+
+impl From<Dedupe> for DuplicateKey {
+    fn from(dedupe: Dedupe) -> Self {
+        match dedupe {
+            Dedupe::TitleVersion => DuplicateKey::TitleVersion,
+            Dedupe::NameSize => DuplicateKey::NameSize,
+            Dedupe::Checksum => DuplicateKey::Checksum,
+        }
+    }
+}
+
+impl From<DedupePolicy> for DuplicatePolicy {
+    fn from(policy: DedupePolicy) -> Self {
+        match policy {
+            DedupePolicy::First => DuplicatePolicy::PreferFirst,
+            DedupePolicy::Smaller => DuplicatePolicy::PreferSmaller,
+            DedupePolicy::Nsz => DuplicatePolicy::PreferNsz,
+        }
+    }
+}
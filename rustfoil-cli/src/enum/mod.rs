@@ -1 +1,4 @@
 pub mod compression;
+pub mod duplicate;
+pub mod report;
+pub mod walk;
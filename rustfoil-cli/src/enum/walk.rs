@@ -0,0 +1,42 @@
+use clap::ValueEnum;
+use rustfoil_lib::fs::file::{NonUtf8Policy, SymlinkPolicy};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Symlinks {
+    /// Follows symlinks to files & directories, skipping loops
+    Follow,
+    /// Follows symlinks to files only
+    FilesOnly,
+    /// Ignores all symlinks
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum NonUtf8Names {
+    /// Replaces invalid bytes with U+FFFD
+    Lossy,
+    /// Percent-encodes invalid bytes
+    PercentEncode,
+    /// Skips the file with a warning
+    Skip,
+}
+
+impl From<Symlinks> for SymlinkPolicy {
+    fn from(symlinks: Symlinks) -> Self {
+        match symlinks {
+            Symlinks::Follow => SymlinkPolicy::Follow,
+            Symlinks::FilesOnly => SymlinkPolicy::FilesOnly,
+            Symlinks::Skip => SymlinkPolicy::Skip,
+        }
+    }
+}
+
+impl From<NonUtf8Names> for NonUtf8Policy {
+    fn from(names: NonUtf8Names) -> Self {
+        match names {
+            NonUtf8Names::Lossy => NonUtf8Policy::Lossy,
+            NonUtf8Names::PercentEncode => NonUtf8Policy::PercentEncode,
+            NonUtf8Names::Skip => NonUtf8Policy::Skip,
+        }
+    }
+}
@@ -3,21 +3,50 @@ mod r#enum;
 
 use crate::cli::model::IndexCommand;
 use crate::r#enum::compression::Compression;
+use crate::r#enum::report::ReportFormat;
 use clap::Parser;
-use cli::model::{Cli, Commands, GoogleDriveCommand, HttpCommand};
+use cli::model::{
+    CertgenCommand, Cli, Commands, GoogleDriveCommand, HttpCommand, InspectCommand, KeygenCommand,
+    ServeCommand,
+};
 use env_logger::Env;
 use hhmmss::Hhmmss;
 use log::{debug, info, warn};
 use rustfoil_lib::abstraction::file::TinfoilFileLike;
-use rustfoil_lib::filter::file::filter_files;
-use rustfoil_lib::fs::file::read_files_recursive;
+use rustfoil_lib::filter::duplicate::{dedupe_files, DuplicateKey};
+use rustfoil_lib::filter::file::{
+    ExtensionFilter, FilterPipeline, NameRegexFilter, PathGlobFilter, SizeFilter, TitleIdFilter,
+};
+use rustfoil_lib::filter::report::{format_table, FilterReason, RejectedFile};
+use rustfoil_lib::filter::title::{TitleIdList, TitleListFilter};
+use rustfoil_lib::filter::update::keep_latest_updates;
+use rustfoil_lib::fs::container::read_title_info;
+use rustfoil_lib::fs::file::WalkOptions;
+use rustfoil_lib::fs::stream::stream_files;
+use rustfoil_lib::fs::verify::verify_file;
 use rustfoil_lib::gdrive::gdrive_api::GoogleDriveApiService;
 use rustfoil_lib::gdrive::service::GoogleDriveService;
+use rustfoil_lib::http::auth::{AuthError, TinfoilAuth};
+use rustfoil_lib::http::crawl::{crawl_listing, CrawlOptions};
 use rustfoil_lib::http::model::HttpFile;
+use rustfoil_lib::http::server::TinfoilServer;
+use rustfoil_lib::http::tls::TinfoilTls;
 use rustfoil_lib::tinfoil::auth::copy_tinfoil_auth_files;
+use rustfoil_lib::tinfoil::builder::TinfoilIndexBuilder;
+use rustfoil_lib::tinfoil::certificate::{
+    issue_client_certificate, read_client_certificate, TinfoilServerCertificates,
+};
+use rustfoil_lib::tinfoil::directory::group_files_by_folder;
 use rustfoil_lib::tinfoil::encryption::encryption::TinfoilEncryption;
+use rustfoil_lib::tinfoil::encryption::keygen::{TinfoilKeyPair, KEY_BITS};
+use rustfoil_lib::tinfoil::location::read_locations;
+use rustfoil_lib::tinfoil::model::{TinfoilIndex, TinfoilLocation};
 use rustfoil_lib::tinfoil::service::TinfoilService;
+use rustfoil_lib::tinfoil::user::{TinfoilUser, TinfoilUsers};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use tokio::time::Instant;
+use tokio_stream::StreamExt;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -26,8 +55,12 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
+        Commands::Certgen(cmd) => certgen(cmd).await?,
         Commands::Gdrive(cmd) => gdrive(cmd).await?,
         Commands::Http(cmd) => http(cmd).await?,
+        Commands::Inspect(cmd) => inspect(cmd).await?,
+        Commands::Keygen(cmd) => keygen(cmd).await?,
+        Commands::Serve(cmd) => serve(cmd).await?,
     }
 
     info!("Execution took {}", timer.elapsed().hhmmss());
@@ -56,7 +89,11 @@ async fn gdrive(command: GoogleDriveCommand) -> anyhow::Result<()> {
     info!("Scanning Google Drive... this may take a while");
 
     let scan = gdrive_service
-        .scan_folders(command.folder_ids.clone(), command.no_recursion.clone())
+        .scan_folders(
+            command.folder_ids.clone(),
+            command.no_recursion.clone(),
+            command.no_ignore_files,
+        )
         .await?;
 
     let scan_files_length = scan.files.len();
@@ -70,8 +107,14 @@ async fn gdrive(command: GoogleDriveCommand) -> anyhow::Result<()> {
     build_and_write_index(
         command.clone(),
         &tinfoil_service,
+        &build_filter_pipeline(&command).await?,
         scan.files.clone(),
+        Vec::new(),
         scan_files_length,
+        Some(SubIndexUpload {
+            service: &gdrive_service,
+            share: command.share_index,
+        }),
     )
     .await?;
 
@@ -135,35 +178,318 @@ async fn gdrive(command: GoogleDriveCommand) -> anyhow::Result<()> {
 }
 
 async fn http(command: HttpCommand) -> anyhow::Result<()> {
+    if !command.crawl {
+        info!(
+            "Generating Index for {} Local Folders",
+            command.folder_paths.len()
+        );
+
+        debug!("Folder Paths: {:?}", command.folder_paths);
+    }
+
+    let tinfoil_service = TinfoilService::new();
+    let pipeline = build_filter_pipeline(&command).await?;
+
+    let (files, rejected) = match command.crawl {
+        true => (crawl_http_listing(&command).await?, Vec::new()),
+        false => scan_local_folders(&command, &pipeline).await?,
+    };
+
+    let file_length = files.len() + rejected.len();
+
+    // local files already went through the pipeline while scanning
+    let pipeline = match command.crawl {
+        true => pipeline,
+        false => FilterPipeline::new(),
+    };
+
+    build_and_write_index(
+        command,
+        &tinfoil_service,
+        &pipeline,
+        files,
+        rejected,
+        file_length,
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn serve(command: ServeCommand) -> anyhow::Result<()> {
     info!(
-        "Generating Index for {} Local Folders",
-        command.folder_paths.len()
+        "Serving Index for {} Local Folders",
+        command.http.folder_paths.len()
     );
 
-    debug!("Folder Paths: {:?}", command.folder_paths);
+    debug!("Folder Paths: {:?}", command.http.folder_paths);
+
+    if command.http.crawl {
+        return Err(anyhow::anyhow!(
+            "--crawl can't be used when serving, only local folders can be served"
+        ));
+    }
+
+    if command.http.index_per_folder {
+        warn!("--index-per-folder is ignored when serving, all files are listed in one Index");
+    }
 
     let tinfoil_service = TinfoilService::new();
 
+    let pipeline = build_filter_pipeline(&command.http).await?;
+    let (files, rejected) = scan_local_folders(&command.http, &pipeline).await?;
+
+    let file_length = files.len() + rejected.len();
+
+    let filtered = filter_index_files(
+        &command.http,
+        &FilterPipeline::new(),
+        files,
+        rejected,
+        file_length,
+    )
+    .await?;
+
+    let mut builder = configure_index_builder(&command.http)
+        .await?
+        .locations(read_index_locations(&command.http).await?);
+
+    if let (Some(ca_cert), Some(ca_key)) = (&command.client_ca, &command.client_ca_key) {
+        builder = builder.client_certificate(issue_client_certificate(ca_cert, ca_key).await?);
+
+        info!("Issued a client certificate signed by {:?}", ca_cert);
+    }
+
+    let index = builder
+        .clone()
+        .files(&filtered)
+        .success(command.http.success())
+        .build()?;
+
+    let index_data = encode_index_file(&command.http, &tinfoil_service, index, None).await?;
+
+    info!(
+        "Index with {} file(s) generated in memory, serving files below {}",
+        filtered.len(),
+        command.http.http_base_url
+    );
+
+    let mut server =
+        TinfoilServer::new(command.http.http_base_url.as_str(), index_data, &filtered)?;
+
+    if let Some(users_path) = command.http.users() {
+        let users = TinfoilUsers::read(&users_path).await?;
+
+        info!("Requiring authentication for {} user(s)", users.users.len());
+
+        let mut error_indexes = HashMap::new();
+
+        for error in AuthError::ALL {
+            let index = TinfoilIndexBuilder::new()
+                .error(error.to_string())
+                .build()?;
+
+            error_indexes.insert(
+                error,
+                encode_index_file(&command.http, &tinfoil_service, index, None).await?,
+            );
+        }
+
+        let mut user_indexes = HashMap::new();
+
+        for user in users.users.iter().filter(|user| user.has_own_index()) {
+            let (file_count, data) = encode_user_index_file(
+                &command.http,
+                &tinfoil_service,
+                builder.clone(),
+                &filtered,
+                user,
+            )
+            .await?;
+
+            debug!(
+                "Index for user {} with {} file(s) generated in memory",
+                user.username, file_count
+            );
+
+            user_indexes.insert(user.username.clone(), data);
+        }
+
+        server = server.with_auth(TinfoilAuth {
+            users,
+            error_indexes,
+            user_indexes,
+        });
+    }
+
+    if let (Some(cert), Some(key)) = (&command.tls_cert, &command.tls_key) {
+        let tls = TinfoilTls::read(cert, key, command.client_ca.as_deref()).await?;
+
+        if tls.client_auth && command.client_ca_key.is_none() && command.http.client_cert.is_none()
+        {
+            warn!("Files require a client certificate but the Index doesn't contain one");
+        }
+
+        if tls.client_auth && command.http.client_cert.is_some() && command.http.users.is_none() {
+            warn!("The Index is served to anyone without --users, so is the client certificate embedded in it");
+        }
+
+        server = server.with_tls(tls);
+    }
+
+    server.serve(command.bind).await
+}
+
+/// Reads the files from the directory listing at the base url
+async fn crawl_http_listing(command: &HttpCommand) -> anyhow::Result<Vec<HttpFile>> {
+    info!(
+        "Crawling Directory Listing at {}... this may take a while",
+        command.http_base_url
+    );
+
+    if command.inspect_containers
+        || command.verify
+        || command.verify_report.is_some()
+        || command.exclude_invalid
+    {
+        warn!("Containers can't be inspected or verified while crawling, the files aren't local");
+    }
+
+    let options = CrawlOptions {
+        max_depth: match command.no_recursion {
+            true => Some(0),
+            false => command.max_depth,
+        },
+        workers: command.scan_workers,
+    };
+
+    let result = crawl_listing(command.http_base_url.as_str(), &options).await?;
+
+    for warning in &result.warnings {
+        warn!("Skipped {}", warning);
+    }
+
+    info!(
+        "Crawled a total of {} file(s), {} warning(s)",
+        result.files.len(),
+        result.warnings.len()
+    );
+
+    Ok(result.files)
+}
+
+/// Scans the local folders of the command, applying the pipeline to every file as soon as it's found
+async fn scan_local_folders(
+    command: &HttpCommand,
+    pipeline: &FilterPipeline,
+) -> anyhow::Result<(Vec<HttpFile>, Vec<RejectedFile>)> {
     info!("Scanning Local Folders... this may take a while");
 
+    let walk_options = WalkOptions {
+        max_depth: match command.no_recursion {
+            true => Some(0),
+            false => command.max_depth,
+        },
+        include_dirs: command.include_dir.clone(),
+        exclude_dirs: command.exclude_dir.clone(),
+        symlinks: command.symlinks.into(),
+        include_hidden: command.include_hidden,
+        include_partial: command.include_partial,
+        non_utf8_names: command.non_utf8_names.into(),
+        no_ignore_files: command.no_ignore_files,
+    };
+
+    let verify = command.verify || command.verify_report.is_some() || command.exclude_invalid;
+
     let mut files = Vec::new();
+    let mut rejected = Vec::new();
+    let mut verified = Vec::new();
+    let mut warnings = 0;
 
     for folder_path in &command.folder_paths {
-        let folder_files = read_files_recursive(folder_path).await?;
+        let mut stream =
+            stream_files(folder_path, walk_options.clone(), command.scan_workers).await?;
 
-        let mut http_files: Vec<HttpFile> = folder_files
-            .iter()
-            .map(|local| {
-                HttpFile::from_local_with_base_url(
-                    command.http_base_url.as_str(),
-                    folder_path,
-                    local,
-                )
-                .unwrap()
-            })
-            .collect();
+        let mut folder_files = Vec::new();
 
-        files.append(&mut http_files)
+        while let Some(item) = stream.next().await {
+            let local = match item {
+                Ok(local) => local,
+                Err(warning) => {
+                    warn!("Skipped {}", warning);
+                    warnings += 1;
+                    continue;
+                }
+            };
+
+            let mut file = match HttpFile::from_local_with_base_url(
+                command.http_base_url.as_str(),
+                folder_path,
+                &local,
+            ) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Skipped {:?}: {}", local.path, e);
+                    warnings += 1;
+                    continue;
+                }
+            };
+
+            if command.inspect_containers && file.get_title_info().is_none() {
+                match read_title_info(&local.path).await {
+                    Ok(Some(info)) => {
+                        debug!("Read Title ID {} from {:?}", info.title_id, local.path);
+                        file = file.with_title_info(info);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to inspect {:?}: {:#}", local.path, e),
+                }
+            }
+
+            if let Some(reason) = pipeline.check(&file) {
+                rejected.push(RejectedFile::new(&file, reason));
+                continue;
+            }
+
+            if verify {
+                match verify_file(&local.path).await {
+                    Ok(Some(result)) => {
+                        let is_ok = result.is_ok();
+
+                        if !is_ok {
+                            warn!("{:?} failed verification: {}", local.path, result);
+                        }
+
+                        if !is_ok && command.exclude_invalid {
+                            rejected.push(RejectedFile::new(
+                                &file,
+                                FilterReason::InvalidContainer {
+                                    problem: result.to_string(),
+                                },
+                            ));
+                            verified.push(result);
+                            continue;
+                        }
+
+                        verified.push(result);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to verify {:?}: {}", local.path, e),
+                }
+            }
+
+            folder_files.push(file);
+        }
+
+        // workers finish in any order, sorting keeps the index stable between runs
+        folder_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        files.append(&mut folder_files);
+    }
+
+    if warnings > 0 {
+        warn!("Skipped {} unreadable entries while scanning", warnings);
     }
 
     info!(
@@ -172,59 +498,503 @@ async fn http(command: HttpCommand) -> anyhow::Result<()> {
         command.folder_paths.len()
     );
 
-    let file_length = files.len();
+    if verify {
+        let invalid = verified.iter().filter(|result| !result.is_ok()).count();
 
-    build_and_write_index(command, &tinfoil_service, files, file_length).await?;
+        info!(
+            "Verified {} container(s), {} of them are invalid",
+            verified.len(),
+            invalid
+        );
+
+        if let Some(report_path) = &command.verify_report {
+            tokio::fs::write(report_path, serde_json::to_vec_pretty(&verified)?).await?;
+
+            info!("Wrote verify report to {:?}", report_path);
+        }
+    }
+
+    Ok((files, rejected))
+}
+
+async fn inspect(command: InspectCommand) -> anyhow::Result<()> {
+    let tinfoil_service = TinfoilService::new();
+
+    let file_data = tokio::fs::read(&command.index_path).await?;
+
+    let header = tinfoil_service.read_index_header(&file_data)?;
+
+    if !command.json {
+        println!("Index: {:?}", &command.index_path);
+        println!("Compression: {:?}", header.compression);
+        println!("Encryption: {:?}", header.encryption);
+        println!("Data Length: {} bytes", header.data_length);
+
+        if let (TinfoilEncryption::Encrypt, None) = (header.encryption, &command.private_key) {
+            warn!("Index is encrypted, provide --private-key to decode its content");
+            return Ok(());
+        }
+    }
+
+    let index = tinfoil_service
+        .read_index_file(&file_data, command.private_key.clone())
+        .await?;
+
+    if command.json {
+        println!("{}", serde_json::to_string_pretty(&index)?);
+        return Ok(());
+    }
+
+    if let Some(files) = &index.files {
+        let total_size: i64 = files.iter().map(|file| file.size).sum();
+        println!("Files: {} ({} bytes)", files.len(), total_size);
+    }
+
+    if let Some(directories) = &index.directories {
+        println!("Directories: {}", directories.len());
+    }
+
+    if let Some(locations) = &index.locations {
+        println!("Locations: {}", locations.len());
+    }
+
+    if let Some(success) = &index.success {
+        println!("Success: {}", success);
+    }
+
+    if let Some(error) = &index.error {
+        println!("Error: {}", error);
+    }
+
+    if let Some(referrer) = &index.referrer {
+        println!("Referrer: {}", referrer);
+    }
+
+    if let Some(headers) = &index.headers {
+        println!("Headers: {}", headers.join(", "));
+    }
+
+    if let Some(version) = &index.version {
+        println!("Minimum Version: {}", version);
+    }
+
+    if let Some(theme_error) = &index.theme_error {
+        println!("Theme Error: {}", theme_error);
+    }
+
+    Ok(())
+}
+
+async fn certgen(command: CertgenCommand) -> anyhow::Result<()> {
+    info!(
+        "Generating CA & server certificate for {}",
+        command.hostnames.join(", ")
+    );
+
+    let certificates = TinfoilServerCertificates::generate(command.hostnames.clone())?;
+
+    certificates
+        .write(&command.output_path, command.force)
+        .await?;
+
+    info!(
+        "Certificates written to {:?}, pass ca.pem & ca.key to serve via --client-ca & --client-ca-key",
+        &command.output_path
+    );
+
+    Ok(())
+}
+
+async fn keygen(command: KeygenCommand) -> anyhow::Result<()> {
+    info!("Generating {} bit RSA key pair", KEY_BITS);
+
+    let key_pair = tokio::task::spawn_blocking(TinfoilKeyPair::generate).await??;
+
+    key_pair
+        .write(&command.public_key, &command.private_key, command.force)
+        .await?;
+
+    info!(
+        "Public Key written to {:?}, Private Key written to {:?}",
+        &command.public_key, &command.private_key
+    );
 
     Ok(())
 }
 
+/// Google Drive folders can only list sub Indexes which were uploaded to Google Drive
+struct SubIndexUpload<'a> {
+    service: &'a GoogleDriveService,
+    share: bool,
+}
+
 async fn build_and_write_index<F, C>(
     command: C,
     tinfoil_service: &TinfoilService,
+    pipeline: &FilterPipeline,
     files: Vec<F>,
+    rejected: Vec<RejectedFile>,
     file_count: usize,
+    sub_index_upload: Option<SubIndexUpload<'_>>,
 ) -> anyhow::Result<()>
 where
     F: TinfoilFileLike,
     C: IndexCommand,
 {
-    let filtered = filter_files(
-        files,
-        command.add_non_nsw_files(),
-        command.add_nsw_files_without_title_id(),
+    let filtered = filter_index_files(&command, pipeline, files, rejected, file_count).await?;
+    let locations = read_index_locations(&command).await?;
+    let builder = configure_index_builder(&command).await?;
+
+    if let Some(users_path) = command.users() {
+        let user_builder = builder.clone().locations(locations.clone());
+
+        write_user_index_files(
+            &command,
+            tinfoil_service,
+            user_builder,
+            &filtered,
+            &users_path,
+        )
+        .await?;
+    }
+
+    let output_path = command.output_path();
+
+    let index = match command.index_per_folder() {
+        false => builder
+            .files(&filtered)
+            .success(command.success())
+            .locations(locations)
+            .build()?,
+        true => {
+            let output_dir = match output_path.parent() {
+                None => {
+                    return Err(anyhow::Error::msg("Output path has no parent"));
+                }
+                Some(dir) => dir,
+            };
+
+            let output_file_name = match output_path.file_name() {
+                None => {
+                    return Err(anyhow::Error::msg("Output path has no file name"));
+                }
+                Some(name) => name,
+            };
+
+            let mut root_files = Vec::new();
+            let mut directories = Vec::new();
+            let mut sub_index_folders = HashMap::new();
+
+            for folder in group_files_by_folder(filtered) {
+                if folder.path.is_empty() {
+                    root_files.extend(folder.files);
+                    continue;
+                }
+
+                let upload = sub_index_upload.as_ref().zip(folder.gdrive_folder_id());
+
+                let mut folder_path = folder.local_path()?;
+                let mut duplicate = 1;
+
+                while let Some(other_url) = sub_index_folders.get(&folder_path) {
+                    // uploaded Sub Indexes are listed by their own url, others have to be placed where their folder is served
+                    if upload.is_none() {
+                        return Err(anyhow::anyhow!(
+                            "Sub Indexes of {} & {} would both be written to {:?}",
+                            other_url,
+                            &folder.url,
+                            output_dir.join(&folder_path)
+                        ));
+                    }
+
+                    duplicate += 1;
+                    folder_path = folder.local_path()?;
+                    folder_path.set_file_name(format!(
+                        "{} ({})",
+                        folder_path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy(),
+                        duplicate
+                    ));
+                }
+
+                sub_index_folders.insert(folder_path.clone(), folder.url.clone());
+
+                let sub_index_path = output_dir.join(&folder_path).join(output_file_name);
+
+                let sub_index = builder.clone().files(&folder.files).build()?;
+
+                write_index_file(&command, tinfoil_service, sub_index, &sub_index_path).await?;
+
+                debug!(
+                    "Sub Index for {} with {} file(s) generated at {:?}",
+                    &folder.url,
+                    folder.files.len(),
+                    &sub_index_path
+                );
+
+                let sub_index_url = match upload {
+                    Some((upload, folder_id)) => {
+                        let file = upload
+                            .service
+                            .upload_index(sub_index_path, Some(folder_id.to_string()))
+                            .await?;
+
+                        if upload.share {
+                            upload.service.share_index(&file.id).await?;
+                        }
+
+                        debug!("Uploaded Sub Index for {} to {}", &folder.url, folder_id);
+
+                        file.get_url()
+                    }
+                    None => folder.sub_index_url(&output_file_name.to_string_lossy()),
+                };
+
+                directories.push(sub_index_url);
+            }
+
+            info!(
+                "Generated {} Sub Index file(s), {} file(s) remain in the root Index",
+                directories.len(),
+                root_files.len()
+            );
+
+            builder
+                .files(&root_files)
+                .directories(directories)
+                .success(command.success())
+                .locations(locations)
+                .build()?
+        }
+    };
+
+    write_index_file(&command, tinfoil_service, index, output_path).await?;
+
+    let compression = command.compression().unwrap_or(Compression::OFF);
+
+    info!(
+        "Index file generated at {:?}, using {} compression & {}encryption",
+        &output_path,
+        match compression {
+            Compression::OFF => "no".to_string(),
+            Compression::ZSTD | Compression::ZLIB => {
+                compression.to_string()
+            }
+        },
+        match command.encrypt() {
+            false => "no ",
+            true => "",
+        }
     );
 
+    Ok(())
+}
+
+/// Runs all filters, writes the filter & duplicate reports and returns the files to index
+async fn filter_index_files<F, C>(
+    command: &C,
+    pipeline: &FilterPipeline,
+    files: Vec<F>,
+    mut rejected: Vec<RejectedFile>,
+    file_count: usize,
+) -> anyhow::Result<Vec<F>>
+where
+    F: TinfoilFileLike,
+    C: IndexCommand,
+{
+    let result = pipeline.filter(files);
+    rejected.extend(result.rejected);
+    let filtered = result.files;
+
     debug!("{} of {} passed filters", filtered.len(), file_count);
 
-    let index = tinfoil_service.generate_index(
-        Some(&filtered),
-        None,
-        command.success(),
-        command.referrer(),
-        command.google_api_key(),
-        None,
-        command.headers(),
-        command.min_version(),
-        command.theme_blacklist(),
-        command.theme_whitelist(),
-        command.theme_error(),
-    )?;
-
-    let encryption = match command.encrypt() {
-        true => TinfoilEncryption::Encrypt,
-        false => TinfoilEncryption::NoEncrypt,
+    let filtered = match command.keep_latest_updates() {
+        None => filtered,
+        Some(keep) => {
+            let result = keep_latest_updates(filtered, keep);
+
+            debug!(
+                "Removed {} outdated update(s), keeping the {} newest per title",
+                result.rejected.len(),
+                keep
+            );
+
+            rejected.extend(result.rejected);
+            result.files
+        }
     };
 
-    let compression = command.compression().unwrap_or(Compression::OFF);
+    let filtered = match command.dedupe() {
+        None => filtered,
+        Some(dedupe) => {
+            let keys: Vec<DuplicateKey> = dedupe.into_iter().map(|key| key.into()).collect();
 
-    let tinfoil_compression = compression.into();
+            let result = dedupe_files(filtered, &keys, command.dedupe_policy().into());
 
-    let file_data = tinfoil_service
-        .generate_index_file(index, tinfoil_compression, encryption, command.public_key())
-        .await?;
+            for dropped in &result.rejected {
+                debug!("Removed {}, {}", dropped.url, dropped.reason);
+            }
 
-    let output_path = command.output_path();
+            info!("Removed {} duplicate file(s)", result.rejected.len());
+
+            if let Some(report_path) = command.dedupe_report() {
+                tokio::fs::write(&report_path, serde_json::to_vec_pretty(&result.rejected)?)
+                    .await?;
+
+                info!("Duplicate report written to {:?}", report_path);
+            }
+
+            rejected.extend(result.rejected);
+            result.files
+        }
+    };
+
+    if !rejected.is_empty() {
+        let mut reasons: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for file in &rejected {
+            *reasons.entry(file.reason.kind()).or_default() += 1;
+        }
+
+        info!(
+            "Excluded {} file(s): {}",
+            rejected.len(),
+            reasons
+                .iter()
+                .map(|(reason, count)| format!("{} {}", count, reason))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    if let Some(report_path) = command.filter_report() {
+        let format = command.filter_report_format().unwrap_or(
+            match report_path.extension().and_then(|extension| extension.to_str()) {
+                Some("json") => ReportFormat::Json,
+                _ => ReportFormat::Table,
+            },
+        );
+
+        let report = match format {
+            ReportFormat::Json => serde_json::to_vec_pretty(&rejected)?,
+            ReportFormat::Table => format_table(&rejected).into_bytes(),
+        };
+
+        tokio::fs::write(&report_path, report).await?;
+
+        info!("Filter report written to {:?}", report_path);
+    }
+
+    Ok(filtered)
+}
+
+async fn read_index_locations<C>(command: &C) -> anyhow::Result<Option<Vec<TinfoilLocation>>>
+where
+    C: IndexCommand,
+{
+    match command.location_path() {
+        None => Ok(None),
+        Some(path) => {
+            let locations = read_locations(&path).await?;
+
+            debug!("Loaded {} location(s) from {:?}", locations.len(), path);
+
+            Ok(Some(locations))
+        }
+    }
+}
+
+/// Builder with all options shared between the root Index & sub Indexes
+async fn configure_index_builder<C>(command: &C) -> anyhow::Result<TinfoilIndexBuilder>
+where
+    C: IndexCommand,
+{
+    let client_certificate = match (command.client_cert(), command.client_key()) {
+        (Some(cert_path), Some(key_path)) => {
+            Some(read_client_certificate(&cert_path, &key_path).await?)
+        }
+        _ => None,
+    };
+
+    Ok(TinfoilIndexBuilder::new()
+        .referrer(command.referrer())
+        .google_api_key(command.google_api_key())
+        .one_fichier_keys(command.one_fichier_keys())
+        .headers(command.headers())
+        .version(command.min_version())
+        .theme_blacklist(command.theme_blacklist())
+        .theme_whitelist(command.theme_whitelist())
+        .theme_error(command.theme_error())
+        .client_certificate(client_certificate))
+}
+
+async fn build_filter_pipeline<C>(command: &C) -> anyhow::Result<FilterPipeline>
+where
+    C: IndexCommand,
+{
+    let mut pipeline = FilterPipeline::new();
+
+    if !command.add_non_nsw_files() {
+        pipeline = pipeline.with(ExtensionFilter::nsw());
+    }
+
+    if !command.add_nsw_files_without_title_id() {
+        pipeline = pipeline.with(TitleIdFilter::new());
+    }
+
+    if !command.include().is_empty() || !command.exclude().is_empty() {
+        pipeline = pipeline.with(NameRegexFilter::new(
+            &command.include(),
+            &command.exclude(),
+        )?);
+    }
+
+    if !command.include_path().is_empty() || !command.exclude_path().is_empty() {
+        pipeline = pipeline.with(PathGlobFilter::new(
+            &command.include_path(),
+            &command.exclude_path(),
+        )?);
+    }
+
+    if command.min_size().is_some() || command.max_size().is_some() {
+        pipeline = pipeline.with(SizeFilter::new(command.min_size(), command.max_size()));
+    }
+
+    if command.title_allowlist().is_some() || command.title_blocklist().is_some() {
+        let allowlist = match command.title_allowlist() {
+            None => None,
+            Some(path) => Some(TitleIdList::read(&path).await?),
+        };
+
+        let blocklist = match command.title_blocklist() {
+            None => None,
+            Some(path) => Some(TitleIdList::read(&path).await?),
+        };
+
+        debug!(
+            "Loaded {} allowlist & {} blocklist Title ID entries",
+            allowlist.as_ref().map_or(0, |list| list.len()),
+            blocklist.as_ref().map_or(0, |list| list.len())
+        );
+
+        pipeline = pipeline.with(TitleListFilter::new(allowlist, blocklist));
+    }
+
+    Ok(pipeline)
+}
+
+async fn write_index_file<C>(
+    command: &C,
+    tinfoil_service: &TinfoilService,
+    index: TinfoilIndex,
+    output_path: &PathBuf,
+) -> anyhow::Result<()>
+where
+    C: IndexCommand,
+{
+    let file_data = encode_index_file(command, tinfoil_service, index, None).await?;
 
     let output_dir = match output_path.parent() {
         None => {
@@ -236,19 +1006,114 @@ where
     tokio::fs::create_dir_all(output_dir).await?;
     tokio::fs::write(&output_path, file_data).await?;
 
-    info!(
-        "Index file generated at {:?}, using {} compression & {}encryption",
-        &output_path,
-        match compression {
-            Compression::OFF => "no".to_string(),
-            Compression::ZSTD | Compression::ZLIB => {
-                compression.to_string()
-            }
+    Ok(())
+}
+
+/// Compresses & encrypts the index as configured by the command, a given public key always encrypts
+async fn encode_index_file<C>(
+    command: &C,
+    tinfoil_service: &TinfoilService,
+    index: TinfoilIndex,
+    public_key: Option<PathBuf>,
+) -> anyhow::Result<Vec<u8>>
+where
+    C: IndexCommand,
+{
+    let (encryption, public_key) = match public_key {
+        Some(public_key) => (TinfoilEncryption::Encrypt, Some(public_key)),
+        None => match command.encrypt() {
+            true => (TinfoilEncryption::Encrypt, command.public_key()),
+            false => (TinfoilEncryption::NoEncrypt, None),
         },
-        match encryption {
-            TinfoilEncryption::NoEncrypt => "no ",
-            TinfoilEncryption::Encrypt => "",
+    };
+
+    let compression = command.compression().unwrap_or(Compression::OFF);
+
+    let tinfoil_compression = compression.into();
+
+    tinfoil_service
+        .generate_index_file(index, tinfoil_compression, encryption, public_key)
+        .await
+}
+
+/// Index of a single user, limited to its Title ID allowlist & with its own success message, headers & key
+async fn encode_user_index_file<F, C>(
+    command: &C,
+    tinfoil_service: &TinfoilService,
+    builder: TinfoilIndexBuilder,
+    files: &[F],
+    user: &TinfoilUser,
+) -> anyhow::Result<(usize, Vec<u8>)>
+where
+    F: TinfoilFileLike,
+    C: IndexCommand,
+{
+    let user_files = files
+        .iter()
+        .filter(|file| match &user.title_allowlist {
+            None => true,
+            Some(allowlist) => file
+                .get_title_info()
+                .is_some_and(|info| allowlist.contains(&info)),
+        })
+        .collect::<Vec<&F>>();
+
+    let index = builder
+        .files(&user_files)
+        .success(user.success.clone().or(command.success()))
+        .headers(user.headers.clone().or(command.headers()))
+        .build()?;
+
+    let data = encode_index_file(command, tinfoil_service, index, user.public_key.clone()).await?;
+
+    Ok((user_files.len(), data))
+}
+
+/// Writes `<username>.tfl` for every enabled user of the users file
+async fn write_user_index_files<F, C>(
+    command: &C,
+    tinfoil_service: &TinfoilService,
+    builder: TinfoilIndexBuilder,
+    files: &[F],
+    users_path: &Path,
+) -> anyhow::Result<()>
+where
+    F: TinfoilFileLike,
+    C: IndexCommand,
+{
+    let users = TinfoilUsers::read(users_path).await?;
+    let output_dir = command.users_output_path();
+
+    tokio::fs::create_dir_all(output_dir).await?;
+
+    let mut written = 0;
+
+    for user in &users.users {
+        if !user.is_active() {
+            debug!("Skipping disabled or expired user {}", user.username);
+            continue;
         }
+
+        let (file_count, data) =
+            encode_user_index_file(command, tinfoil_service, builder.clone(), files, user).await?;
+
+        let user_index_path = output_dir.join(format!("{}.tfl", user.username));
+
+        tokio::fs::write(&user_index_path, data).await?;
+
+        debug!(
+            "Index for user {} with {} file(s) generated at {:?}",
+            user.username, file_count, &user_index_path
+        );
+
+        written += 1;
+    }
+
+    info!(
+        "Generated {} user Index file(s) in {:?}, {} user(s) skipped",
+        written,
+        output_dir,
+        users.users.len() - written
     );
 
     Ok(())
@@ -0,0 +1,77 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rustfoil_lib::fs::file::{read_files, WalkOptions};
+use rustfoil_lib::fs::stream::stream_files;
+use std::path::PathBuf;
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+
+const FOLDERS: usize = 1_000;
+const FILES_PER_FOLDER: usize = 100;
+
+/// Generates 100k empty files in 1000 title folders grouped into 10 top level folders, reused between runs
+fn generate_tree() -> PathBuf {
+    let root = std::env::temp_dir().join("rustfoil-walk-bench");
+    let marker = root.join(".complete");
+
+    if marker.exists() {
+        return root;
+    }
+
+    for folder in 0..FOLDERS {
+        let path = root
+            .join(format!("group {}", folder % 10))
+            .join(format!("Title {:04}", folder));
+
+        std::fs::create_dir_all(&path).unwrap();
+
+        for file in 0..FILES_PER_FOLDER {
+            let index = (folder * FILES_PER_FOLDER + file) as u64;
+            let name = format!("Game [{:016X}][v0].nsp", 0x0100000000000000 + index * 0x2000);
+
+            std::fs::File::create(path.join(name)).unwrap();
+        }
+    }
+
+    std::fs::File::create(marker).unwrap();
+
+    root
+}
+
+fn walk(c: &mut Criterion) {
+    let root = generate_tree();
+    let runtime = Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("walk 100k files");
+    group.sample_size(10);
+
+    group.bench_function("read_files", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let result = read_files(&root, &WalkOptions::default()).await.unwrap();
+            assert_eq!(result.files.len(), FOLDERS * FILES_PER_FOLDER);
+        })
+    });
+
+    for workers in [4, 16, 64] {
+        group.bench_function(format!("stream_files ({} workers)", workers), |b| {
+            b.to_async(&runtime).iter(|| async {
+                let mut stream = stream_files(&root, WalkOptions::default(), workers)
+                    .await
+                    .unwrap();
+
+                let mut files = 0;
+
+                while let Some(item) = stream.next().await {
+                    item.unwrap();
+                    files += 1;
+                }
+
+                assert_eq!(files, FOLDERS * FILES_PER_FOLDER);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, walk);
+criterion_main!(benches);
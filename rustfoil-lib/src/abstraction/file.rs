@@ -1,5 +1,89 @@
+use crate::title::model::TitleInfo;
+use crate::title::parser::parse_title_info;
+
 pub trait TinfoilFileLike {
     fn get_url(&self) -> String;
     fn get_size(&self) -> i64;
     fn get_name(&self) -> &str;
+    /// Path of the file relative to the scanned folder, separated by `/`
+    fn get_path(&self) -> &str;
+    /// Url of the folder containing the file
+    fn get_folder_url(&self) -> String;
+
+    fn get_title_info(&self) -> Option<TitleInfo> {
+        parse_title_info(self.get_name())
+    }
+
+    /// Checksum of the file content if the source provides one
+    fn get_checksum(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Allows building indexes from borrowed files, e.g. a subset of the scanned files
+impl<T> TinfoilFileLike for &T
+where
+    T: TinfoilFileLike + ?Sized,
+{
+    fn get_url(&self) -> String {
+        (**self).get_url()
+    }
+
+    fn get_size(&self) -> i64 {
+        (**self).get_size()
+    }
+
+    fn get_name(&self) -> &str {
+        (**self).get_name()
+    }
+
+    fn get_path(&self) -> &str {
+        (**self).get_path()
+    }
+
+    fn get_folder_url(&self) -> String {
+        (**self).get_folder_url()
+    }
+
+    fn get_title_info(&self) -> Option<TitleInfo> {
+        (**self).get_title_info()
+    }
+
+    fn get_checksum(&self) -> Option<&str> {
+        (**self).get_checksum()
+    }
+}
+
+/// Allows mixing files of different sources, e.g. `Vec<Box<dyn TinfoilFileLike>>`
+impl<T> TinfoilFileLike for Box<T>
+where
+    T: TinfoilFileLike + ?Sized,
+{
+    fn get_url(&self) -> String {
+        (**self).get_url()
+    }
+
+    fn get_size(&self) -> i64 {
+        (**self).get_size()
+    }
+
+    fn get_name(&self) -> &str {
+        (**self).get_name()
+    }
+
+    fn get_path(&self) -> &str {
+        (**self).get_path()
+    }
+
+    fn get_folder_url(&self) -> String {
+        (**self).get_folder_url()
+    }
+
+    fn get_title_info(&self) -> Option<TitleInfo> {
+        (**self).get_title_info()
+    }
+
+    fn get_checksum(&self) -> Option<&str> {
+        (**self).get_checksum()
+    }
 }
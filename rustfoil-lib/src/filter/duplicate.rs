@@ -0,0 +1,134 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::filter::report::{FilterReason, FilterResult, RejectedFile};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateKey {
+    /// Same Title ID & version parsed from the file name
+    TitleVersion,
+    /// Same file name & size
+    NameSize,
+    /// Same checksum, as reported by the source (e.g. Google Drive md5Checksum)
+    Checksum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keeps the copy found first, i.e. from the first scanned source
+    PreferFirst,
+    /// Keeps the smallest copy
+    PreferSmaller,
+    /// Keeps a compressed copy (NSZ/XCZ) over an uncompressed one
+    PreferNsz,
+}
+
+impl DuplicateKey {
+    fn key_for<T>(&self, file: &T) -> Option<String>
+    where
+        T: TinfoilFileLike,
+    {
+        match self {
+            DuplicateKey::TitleVersion => file
+                .get_title_info()
+                .map(|info| format!("{}:{}", info.title_id, info.version.unwrap_or(0))),
+            DuplicateKey::NameSize => Some(format!(
+                "{}:{}",
+                file.get_name().to_lowercase(),
+                file.get_size()
+            )),
+            DuplicateKey::Checksum => file.get_checksum().map(|checksum| checksum.to_lowercase()),
+        }
+    }
+}
+
+impl DuplicatePolicy {
+    fn pick<T>(&self, files: &[T], candidates: &[usize]) -> usize
+    where
+        T: TinfoilFileLike,
+    {
+        let first = candidates[0];
+
+        match self {
+            DuplicatePolicy::PreferFirst => first,
+            DuplicatePolicy::PreferSmaller => candidates
+                .iter()
+                .copied()
+                .min_by_key(|position| (files[*position].get_size(), *position))
+                .unwrap_or(first),
+            DuplicatePolicy::PreferNsz => candidates
+                .iter()
+                .copied()
+                .find(|position| is_compressed(files[*position].get_name()))
+                .unwrap_or(first),
+        }
+    }
+}
+
+fn is_compressed(file_name: &str) -> bool {
+    let file_name = file_name.to_lowercase();
+
+    file_name.ends_with(".nsz") || file_name.ends_with(".xcz")
+}
+
+/// Removes duplicates found by each key in order, choosing which copy stays with the given policy
+pub fn dedupe_files<T>(files: Vec<T>, keys: &[DuplicateKey], policy: DuplicatePolicy) -> FilterResult<T>
+where
+    T: TinfoilFileLike,
+{
+    let mut alive = vec![true; files.len()];
+    let mut dropped: Vec<(usize, RejectedFile)> = Vec::new();
+
+    for key in keys {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+
+        for (position, file) in files.iter().enumerate() {
+            if !alive[position] {
+                continue;
+            }
+
+            if let Some(value) = key.key_for(file) {
+                match positions.get(&value) {
+                    Some(group) => groups[*group].push(position),
+                    None => {
+                        positions.insert(value, groups.len());
+                        groups.push(vec![position]);
+                    }
+                }
+            }
+        }
+
+        for group in groups.iter().filter(|group| group.len() > 1) {
+            let winner = policy.pick(&files, group);
+
+            for position in group.iter().copied().filter(|position| *position != winner) {
+                alive[position] = false;
+
+                dropped.push((
+                    position,
+                    RejectedFile::new(
+                        &files[position],
+                        FilterReason::Duplicate {
+                            key: *key,
+                            kept_url: files[winner].get_url(),
+                        },
+                    ),
+                ));
+            }
+        }
+    }
+
+    dropped.sort_by_key(|(position, _)| *position);
+
+    FilterResult {
+        files: files
+            .into_iter()
+            .zip(alive)
+            .filter(|(_, alive)| *alive)
+            .map(|(file, _)| file)
+            .collect(),
+        rejected: dropped.into_iter().map(|(_, dropped)| dropped).collect(),
+    }
+}
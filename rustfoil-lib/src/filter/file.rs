@@ -1,45 +1,245 @@
 use crate::abstraction::file::TinfoilFileLike;
-use lazy_static::lazy_static;
+use crate::filter::report::{FilterReason, FilterResult, RejectedFile};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use regex::Regex;
 
 pub static NSW_EXTENSIONS: [&str; 4] = [".nsp", ".nsz", ".xci", ".xcz"];
 
-pub fn filter_files<T>(
-    files: Vec<T>,
-    add_non_nsw_files: bool,
-    add_nsw_files_without_title_id: bool,
-) -> Vec<T>
-where
-    T: TinfoilFileLike,
-{
-    lazy_static! {
-        static ref REGEX: Regex = Regex::new("[0-9A-Fa-f]{16}").unwrap();
+pub trait FileFilter {
+    /// Returns why the file is rejected, or None if it passes the filter
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason>;
+}
+
+/// Keeps files whose name ends with one of the given extensions, case insensitive
+pub struct ExtensionFilter {
+    extensions: Vec<String>,
+}
+
+/// Keeps files with a Title ID in their name
+#[derive(Default)]
+pub struct TitleIdFilter {}
+
+/// Keeps files whose name matches any include (if there are any) & none of the exclude patterns
+pub struct NameRegexFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+/// Keeps files whose relative path matches any include (if there are any) & none of the exclude globs
+pub struct PathGlobFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    exclude_patterns: Vec<String>,
+}
+
+/// Keeps files with a size inside the given bounds, both inclusive
+pub struct SizeFilter {
+    min: Option<i64>,
+    max: Option<i64>,
+}
+
+/// Chains filters in order, a file is kept only if every filter passes it
+#[derive(Default)]
+pub struct FilterPipeline {
+    filters: Vec<Box<dyn FileFilter + Send + Sync>>,
+}
+
+impl ExtensionFilter {
+    pub fn new(extensions: Vec<String>) -> Self {
+        Self {
+            extensions: extensions
+                .into_iter()
+                .map(|extension| extension.to_lowercase())
+                .collect(),
+        }
+    }
+
+    pub fn nsw() -> Self {
+        Self::new(NSW_EXTENSIONS.iter().map(|e| e.to_string()).collect())
     }
+}
+
+impl FileFilter for ExtensionFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        let file_name = file.get_name().to_lowercase();
 
-    let mut result = Vec::new();
+        match self
+            .extensions
+            .iter()
+            .any(|extension| file_name.ends_with(extension.as_str()))
+        {
+            true => None,
+            false => Some(FilterReason::WrongExtension),
+        }
+    }
+}
+
+impl TitleIdFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-    for file in files {
-        let mut keep = true;
+impl FileFilter for TitleIdFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        match file.get_title_info() {
+            Some(_) => None,
+            None => Some(FilterReason::NoTitleId),
+        }
+    }
+}
+
+impl NameRegexFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: include
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+            exclude: exclude
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl FileFilter for NameRegexFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
         let file_name = file.get_name();
 
-        if !add_non_nsw_files {
-            let extension: String = file_name
-                .chars()
-                .skip(file_name.len() - 4)
-                .take(4)
-                .collect();
+        if !self.include.is_empty() && !self.include.iter().any(|regex| regex.is_match(file_name))
+        {
+            return Some(FilterReason::ExcludedByRule {
+                rule: "name matches no include pattern".to_string(),
+            });
+        }
 
-            keep = NSW_EXTENSIONS.contains(&extension.as_str());
+        self.exclude
+            .iter()
+            .find(|regex| regex.is_match(file_name))
+            .map(|regex| FilterReason::ExcludedByRule {
+                rule: format!("name matches exclude pattern {}", regex.as_str()),
+            })
+    }
+}
+
+impl PathGlobFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: match include.is_empty() {
+                true => None,
+                false => Some(build_glob_set(include)?),
+            },
+            exclude: build_glob_set(exclude)?,
+            exclude_patterns: exclude.to_vec(),
+        })
+    }
+}
+
+impl FileFilter for PathGlobFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        let path = file.get_path();
+
+        if let Some(include) = &self.include {
+            if !include.is_match(path) {
+                return Some(FilterReason::ExcludedByRule {
+                    rule: "path matches no include glob".to_string(),
+                });
+            }
         }
 
-        if !add_nsw_files_without_title_id {
-            keep = REGEX.is_match(file_name);
+        self.exclude
+            .matches(path)
+            .first()
+            .map(|position| FilterReason::ExcludedByRule {
+                rule: format!("path matches exclude glob {}", self.exclude_patterns[*position]),
+            })
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+
+    Ok(builder.build()?)
+}
+
+impl SizeFilter {
+    pub fn new(min: Option<i64>, max: Option<i64>) -> Self {
+        Self { min, max }
+    }
+}
+
+impl FileFilter for SizeFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        let size = file.get_size();
+
+        match self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max) {
+            true => None,
+            false => Some(FilterReason::SizeOutOfBounds),
         }
+    }
+}
+
+impl FilterPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        if keep {
-            result.push(file);
+    pub fn with<F>(mut self, filter: F) -> Self
+    where
+        F: FileFilter + Send + Sync + 'static,
+    {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Returns the reason of the first filter rejecting the file, or None if all filters pass it
+    pub fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        self.filters.iter().find_map(|filter| filter.check(file))
+    }
+
+    pub fn filter<T>(&self, files: Vec<T>) -> FilterResult<T>
+    where
+        T: TinfoilFileLike,
+    {
+        let mut result = FilterResult {
+            files: Vec::new(),
+            rejected: Vec::new(),
+        };
+
+        for file in files {
+            match self.check(&file) {
+                None => result.files.push(file),
+                Some(reason) => result.rejected.push(RejectedFile::new(&file, reason)),
+            }
         }
+
+        result
+    }
+}
+
+pub fn filter_files<T>(
+    files: Vec<T>,
+    add_non_nsw_files: bool,
+    add_nsw_files_without_title_id: bool,
+) -> Vec<T>
+where
+    T: TinfoilFileLike,
+{
+    let mut pipeline = FilterPipeline::new();
+
+    if !add_non_nsw_files {
+        pipeline = pipeline.with(ExtensionFilter::nsw());
+    }
+
+    if !add_nsw_files_without_title_id {
+        pipeline = pipeline.with(TitleIdFilter::new());
     }
 
-    return result;
+    pipeline.filter(files).files
 }
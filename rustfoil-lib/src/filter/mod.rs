@@ -1 +1,5 @@
+pub mod duplicate;
 pub mod file;
+pub mod report;
+pub mod title;
+pub mod update;
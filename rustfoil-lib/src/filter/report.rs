@@ -0,0 +1,100 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::filter::duplicate::DuplicateKey;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum FilterReason {
+    WrongExtension,
+    NoTitleId,
+    ExcludedByRule { rule: String },
+    SizeOutOfBounds,
+    OutdatedUpdate,
+    Duplicate { key: DuplicateKey, kept_url: String },
+    InvalidContainer { problem: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedFile {
+    pub name: String,
+    pub path: String,
+    pub url: String,
+    pub size: i64,
+    #[serde(flatten)]
+    pub reason: FilterReason,
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterResult<T> {
+    pub files: Vec<T>,
+    pub rejected: Vec<RejectedFile>,
+}
+
+impl Display for FilterReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterReason::WrongExtension => write!(f, "wrong extension"),
+            FilterReason::NoTitleId => write!(f, "no title id"),
+            FilterReason::ExcludedByRule { rule } => write!(f, "excluded by rule: {}", rule),
+            FilterReason::SizeOutOfBounds => write!(f, "size out of bounds"),
+            FilterReason::OutdatedUpdate => write!(f, "outdated update"),
+            FilterReason::Duplicate { key, kept_url } => {
+                write!(f, "duplicate ({:?}) of {}", key, kept_url)
+            }
+            FilterReason::InvalidContainer { problem } => {
+                write!(f, "invalid container: {}", problem)
+            }
+        }
+    }
+}
+
+impl FilterReason {
+    /// Short name of the reason without details, used to summarize reports
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FilterReason::WrongExtension => "wrong extension",
+            FilterReason::NoTitleId => "no title id",
+            FilterReason::ExcludedByRule { .. } => "excluded by rule",
+            FilterReason::SizeOutOfBounds => "size out of bounds",
+            FilterReason::OutdatedUpdate => "outdated update",
+            FilterReason::Duplicate { .. } => "duplicate",
+            FilterReason::InvalidContainer { .. } => "invalid container",
+        }
+    }
+}
+
+impl RejectedFile {
+    pub fn new(file: &dyn TinfoilFileLike, reason: FilterReason) -> Self {
+        Self {
+            name: file.get_name().to_string(),
+            path: file.get_path().to_string(),
+            url: file.get_url(),
+            size: file.get_size(),
+            reason,
+        }
+    }
+}
+
+/// Formats rejected files as a plain text table with one file per line
+pub fn format_table(rejected: &[RejectedFile]) -> String {
+    let path_width = rejected
+        .iter()
+        .map(|file| file.path.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("PATH".len());
+
+    let mut table = format!("{:<width$}  REASON\n", "PATH", width = path_width);
+
+    for file in rejected {
+        table.push_str(&format!(
+            "{:<width$}  {}\n",
+            file.path,
+            file.reason,
+            width = path_width
+        ));
+    }
+
+    table
+}
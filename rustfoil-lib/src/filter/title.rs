@@ -0,0 +1,119 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::filter::file::FileFilter;
+use crate::filter::report::FilterReason;
+use crate::title::model::TitleInfo;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Set of Title IDs, entries ending in `*` match the base title & all of its updates & DLCs
+#[derive(Debug, Clone, Default)]
+pub struct TitleIdList {
+    title_ids: HashSet<String>,
+    base_title_ids: HashSet<String>,
+}
+
+/// Keeps files whose Title ID is on the allowlist (if there is one) & not on the blocklist
+pub struct TitleListFilter {
+    allowlist: Option<TitleIdList>,
+    blocklist: Option<TitleIdList>,
+}
+
+impl TitleIdList {
+    /// Parses either a JSON array of Title IDs or a text file with one Title ID per line, `#` starts a comment
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let entries: Vec<String> = match content.trim_start().starts_with('[') {
+            true => serde_json::from_str(content)?,
+            false => content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or("").trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect(),
+        };
+
+        Self::from_entries(entries)
+    }
+
+    /// Builds the list from Title IDs, `<base title id>*` matches the base title & all of its updates & DLCs
+    pub fn from_entries(entries: Vec<String>) -> anyhow::Result<Self> {
+        let mut list = TitleIdList::default();
+
+        for entry in entries {
+            let (title_id, wildcard) = match entry.strip_suffix('*') {
+                None => (entry.as_str(), false),
+                Some(title_id) => (title_id, true),
+            };
+
+            let info = match title_id.len() == 16 {
+                true => TitleInfo::new(title_id),
+                false => None,
+            };
+
+            match (info, wildcard) {
+                (None, _) => {
+                    return Err(anyhow::anyhow!("\"{}\" is not a valid Title ID", entry));
+                }
+                (Some(info), true) => {
+                    list.base_title_ids.insert(info.base_title_id);
+                }
+                (Some(info), false) => {
+                    list.title_ids.insert(info.title_id);
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    pub async fn read(path: &PathBuf) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path).await?;
+
+        Self::parse(content.as_str())
+            .map_err(|e| anyhow::anyhow!("Failed to read Title ID list {:?}: {}", path, e))
+    }
+
+    pub fn contains(&self, info: &TitleInfo) -> bool {
+        self.title_ids.contains(&info.title_id) || self.base_title_ids.contains(&info.base_title_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.title_ids.len() + self.base_title_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl TitleListFilter {
+    pub fn new(allowlist: Option<TitleIdList>, blocklist: Option<TitleIdList>) -> Self {
+        Self {
+            allowlist,
+            blocklist,
+        }
+    }
+}
+
+impl FileFilter for TitleListFilter {
+    fn check(&self, file: &dyn TinfoilFileLike) -> Option<FilterReason> {
+        let info = file.get_title_info();
+
+        if let Some(allowlist) = &self.allowlist {
+            if !info.as_ref().is_some_and(|info| allowlist.contains(info)) {
+                return Some(FilterReason::ExcludedByRule {
+                    rule: "title id not on allowlist".to_string(),
+                });
+            }
+        }
+
+        if let (Some(blocklist), Some(info)) = (&self.blocklist, &info) {
+            if blocklist.contains(info) {
+                return Some(FilterReason::ExcludedByRule {
+                    rule: format!("title id {} on blocklist", info.title_id),
+                });
+            }
+        }
+
+        None
+    }
+}
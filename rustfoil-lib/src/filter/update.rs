@@ -0,0 +1,59 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::filter::report::{FilterReason, FilterResult, RejectedFile};
+use crate::title::model::TitleType;
+use std::collections::HashMap;
+
+/// Keeps only the `keep` newest versions of every update, base games & DLCs are left untouched
+pub fn keep_latest_updates<T>(files: Vec<T>, keep: usize) -> FilterResult<T>
+where
+    T: TinfoilFileLike,
+{
+    let versions: Vec<Option<(String, u32)>> = files
+        .iter()
+        .map(|file| match file.get_title_info() {
+            Some(info) if info.title_type == TitleType::Update => {
+                Some((info.title_id, info.version.unwrap_or(0)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut versions_by_title: HashMap<&str, Vec<u32>> = HashMap::new();
+
+    for (title_id, version) in versions.iter().flatten() {
+        versions_by_title
+            .entry(title_id.as_str())
+            .or_default()
+            .push(*version);
+    }
+
+    for title_versions in versions_by_title.values_mut() {
+        title_versions.sort_unstable_by(|a, b| b.cmp(a));
+        title_versions.dedup();
+        title_versions.truncate(keep);
+    }
+
+    let kept: Vec<bool> = versions
+        .iter()
+        .map(|update| match update {
+            None => true,
+            Some((title_id, version)) => versions_by_title[title_id.as_str()].contains(version),
+        })
+        .collect();
+
+    let mut result = FilterResult {
+        files: Vec::new(),
+        rejected: Vec::new(),
+    };
+
+    for (file, keep) in files.into_iter().zip(kept) {
+        match keep {
+            true => result.files.push(file),
+            false => result
+                .rejected
+                .push(RejectedFile::new(&file, FilterReason::OutdatedUpdate)),
+        }
+    }
+
+    result
+}
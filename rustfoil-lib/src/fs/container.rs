@@ -0,0 +1,102 @@
+use crate::fs::partition::{PartitionFormat, PartitionHeader};
+use crate::fs::xci::XciHeader;
+use crate::title::model::TitleInfo;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+/// Extensions of containers starting with a PFS0 partition
+pub const PFS0_EXTENSIONS: [&str; 2] = ["nsp", "nsz"];
+
+/// Extensions of cartridge images
+pub const XCI_EXTENSIONS: [&str; 2] = ["xci", "xcz"];
+
+/// Largest `.cnmt.xml` read, they're usually only a few KB
+const MAX_CNMT_XML_LENGTH: u64 = 0x100000;
+
+/// Reads the Title ID of a local NSP/NSZ/XCI/XCZ from its content, returns `None` for other files or if none could be found
+pub async fn read_title_info(path: &Path) -> anyhow::Result<Option<TitleInfo>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if PFS0_EXTENSIONS.contains(&extension.as_str()) {
+        let mut file = File::open(path).await?;
+        let header = PartitionHeader::read(PartitionFormat::Pfs0, &mut file, 0).await?;
+
+        return read_partition_title_info(&mut file, 0, &header).await;
+    }
+
+    if XCI_EXTENSIONS.contains(&extension.as_str()) {
+        let mut file = File::open(path).await?;
+        let xci = XciHeader::read(&mut file).await?;
+        let (offset, secure) = xci.read_secure_partition(&mut file).await?;
+
+        return read_partition_title_info(&mut file, offset, &secure).await;
+    }
+
+    Ok(None)
+}
+
+/// Prefers the `.cnmt.xml` some dumps include since it carries the version as well.
+/// Falls back to the rights ID in the ticket & certificate names, which starts with the Title ID.
+///
+/// The `.cnmt.nca` every container has isn't read, it is encrypted with keys derived from the console keys
+/// which can't be shipped. Containers with neither a `.cnmt.xml` nor a ticket, e.g. most XCIs & NSPs of
+/// free titles, yield no Title ID.
+pub(crate) async fn read_partition_title_info(
+    file: &mut File,
+    offset: u64,
+    header: &PartitionHeader,
+) -> anyhow::Result<Option<TitleInfo>> {
+    if let Some(entry) = header.find(".cnmt.xml") {
+        if entry.size <= MAX_CNMT_XML_LENGTH {
+            let mut bytes = vec![0u8; entry.size as usize];
+
+            file.seek(SeekFrom::Start(header.entry_offset(offset, entry)?))
+                .await?;
+            file.read_exact(&mut bytes).await?;
+
+            if let Some(info) = parse_cnmt_xml(String::from_utf8_lossy(&bytes).as_ref()) {
+                return Ok(Some(info));
+            }
+        }
+    }
+
+    // multi-title dumps contain one ticket per title, the first one is the main title
+    Ok(header
+        .entries
+        .iter()
+        .filter(|entry| entry.name.ends_with(".tik") || entry.name.ends_with(".cert"))
+        .find_map(|entry| title_info_from_rights_id(entry.name.as_str())))
+}
+
+/// The rights ID is the Title ID followed by the key generation, e.g. `0100abcd000100000000000000000005.tik`
+fn title_info_from_rights_id(name: &str) -> Option<TitleInfo> {
+    let rights_id = name.split('.').next()?;
+
+    if rights_id.len() != 32 || !rights_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    TitleInfo::new(&rights_id[..16])
+}
+
+fn parse_cnmt_xml(xml: &str) -> Option<TitleInfo> {
+    lazy_static! {
+        static ref ID_REGEX: Regex = Regex::new(r"<Id>0x([0-9A-Fa-f]{16})</Id>").unwrap();
+        static ref VERSION_REGEX: Regex = Regex::new(r"<Version>(\d+)</Version>").unwrap();
+    }
+
+    let mut info = TitleInfo::new(ID_REGEX.captures(xml)?.get(1)?.as_str())?;
+
+    info.version = VERSION_REGEX
+        .captures(xml)
+        .and_then(|captures| captures.get(1))
+        .and_then(|version| version.as_str().parse().ok());
+
+    Some(info)
+}
@@ -1,7 +1,19 @@
+use crate::fs::ignore::{IgnoreRules, IGNORE_FILE_NAME};
 use async_recursion::async_recursion;
 
-use std::path::PathBuf;
+use log::debug;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tokio::fs::DirEntry;
+
+/// Extensions of files which are still being downloaded
+pub const PARTIAL_EXTENSIONS: [&str; 3] = ["part", "!qB", "crdownload"];
+
+pub(crate) const DIRECTORY_VISITED: &str = "directory was already visited, skipping symlink loop";
 
 #[derive(Debug, Clone)]
 pub struct LocalFile {
@@ -10,28 +22,377 @@ pub struct LocalFile {
     pub name: String,
 }
 
-#[async_recursion]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Follows symlinks to files & directories, directories already visited are skipped
+    #[default]
+    Follow,
+    /// Follows symlinks to files only
+    FilesOnly,
+    /// Ignores all symlinks
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonUtf8Policy {
+    /// Replaces invalid bytes with U+FFFD
+    #[default]
+    Lossy,
+    /// Percent-encodes invalid bytes
+    PercentEncode,
+    /// Skips the entry with a warning
+    Skip,
+}
+
+/// Limits how far & into which subdirectories the local walker descends
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Number of subdirectory levels to descend, `Some(0)` only reads the top directory
+    pub max_depth: Option<usize>,
+    /// If not empty, only these subdirectories of the scanned folder are walked
+    pub include_dirs: Vec<String>,
+    /// Subdirectories with these names are skipped at any depth
+    pub exclude_dirs: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    /// Walks files & directories starting with a `.`
+    pub include_hidden: bool,
+    /// Walks files ending in one of [PARTIAL_EXTENSIONS]
+    pub include_partial: bool,
+    pub non_utf8_names: NonUtf8Policy,
+    /// Doesn't apply the rules of [IGNORE_FILE_NAME] files
+    pub no_ignore_files: bool,
+}
+
+/// An entry the walker could not read, the rest of the scan continues
+#[derive(Debug, Clone)]
+pub struct WalkWarning {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkResult {
+    pub files: Vec<LocalFile>,
+    pub warnings: Vec<WalkWarning>,
+}
+
+#[cfg(unix)]
+pub(crate) type DirectoryKey = (u64, u64);
+
+#[cfg(not(unix))]
+pub(crate) type DirectoryKey = PathBuf;
+
+struct Walker<'a> {
+    options: &'a WalkOptions,
+    visited: HashSet<DirectoryKey>,
+    result: WalkResult,
+}
+
+impl WalkOptions {
+    fn should_descend(&self, name: &str, depth: usize) -> bool {
+        if self.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            return false;
+        }
+
+        if self.exclude_dirs.iter().any(|dir| dir == name) {
+            return false;
+        }
+
+        depth > 0 || self.include_dirs.is_empty() || self.include_dirs.iter().any(|dir| dir == name)
+    }
+
+    fn is_skipped(&self, name: &str, is_dir: bool) -> bool {
+        if !is_dir && name == IGNORE_FILE_NAME {
+            return true;
+        }
+
+        if !self.include_hidden && name.starts_with('.') {
+            return true;
+        }
+
+        !is_dir
+            && !self.include_partial
+            && name
+                .rsplit_once('.')
+                .is_some_and(|(_, extension)| PARTIAL_EXTENSIONS.contains(&extension))
+    }
+}
+
+impl WalkWarning {
+    pub fn new(path: &Path, message: impl ToString) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for WalkWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
 pub async fn read_files_recursive(folder_path: &PathBuf) -> anyhow::Result<Vec<LocalFile>> {
-    let mut dir = fs::read_dir(&folder_path).await?;
-    let mut files = Vec::new();
+    Ok(read_files(folder_path, &WalkOptions::default()).await?.files)
+}
+
+/// Walks the folder, only failing if the folder itself can't be read
+pub async fn read_files(folder_path: &PathBuf, options: &WalkOptions) -> anyhow::Result<WalkResult> {
+    let metadata = fs::metadata(folder_path).await?;
+
+    if !metadata.is_dir() {
+        return Err(anyhow::anyhow!("{:?} is not a directory", folder_path));
+    }
+
+    let mut walker = Walker {
+        options,
+        visited: HashSet::new(),
+        result: WalkResult::default(),
+    };
+
+    walker.visited.insert(directory_key(folder_path, &metadata)?);
+    walker.walk(folder_path, 0, IgnoreRules::default()).await?;
+
+    Ok(walker.result)
+}
+
+/// What the walker should do with a single directory entry
+pub(crate) enum WalkEntry {
+    File(LocalFile),
+    Directory(PathBuf, DirectoryKey),
+    Skipped,
+    Warning(WalkWarning),
+}
+
+impl WalkEntry {
+    pub(crate) fn warning(path: &Path, message: impl ToString) -> Self {
+        WalkEntry::Warning(WalkWarning::new(path, message))
+    }
+}
+
+async fn read_entry(
+    entry: &DirEntry,
+    options: &WalkOptions,
+    ignore: &IgnoreRules,
+    depth: usize,
+) -> WalkEntry {
+    let path = entry.path();
+
+    let is_symlink = match entry.file_type().await {
+        Ok(file_type) => file_type.is_symlink(),
+        Err(e) => return WalkEntry::warning(&path, e),
+    };
+
+    // fs::metadata follows symlinks, a broken link ends up as an error here as well
+    let metadata = match is_symlink && options.symlinks == SymlinkPolicy::Skip {
+        true => None,
+        false => Some(fs::metadata(&path).await),
+    };
 
-    while let Some(entry) = dir.next_entry().await? {
-        let path = entry.path();
+    classify_entry(path, &entry.file_name(), is_symlink, metadata, options, ignore, depth)
+}
+
+/// Same as [read_entry] but blocking, used to read whole directories at once
+pub(crate) fn read_entry_blocking(
+    entry: &std::fs::DirEntry,
+    options: &WalkOptions,
+    ignore: &IgnoreRules,
+    depth: usize,
+) -> WalkEntry {
+    let path = entry.path();
+
+    let is_symlink = match entry.file_type() {
+        Ok(file_type) => file_type.is_symlink(),
+        Err(e) => return WalkEntry::warning(&path, e),
+    };
+
+    let metadata = match is_symlink && options.symlinks == SymlinkPolicy::Skip {
+        true => None,
+        false => Some(std::fs::metadata(&path)),
+    };
+
+    classify_entry(path, &entry.file_name(), is_symlink, metadata, options, ignore, depth)
+}
+
+/// Applies the [WalkOptions] to an entry found at `depth`, directories are only returned if they should be descended into
+fn classify_entry(
+    path: PathBuf,
+    file_name: &OsStr,
+    is_symlink: bool,
+    metadata: Option<std::io::Result<Metadata>>,
+    options: &WalkOptions,
+    ignore: &IgnoreRules,
+    depth: usize,
+) -> WalkEntry {
+    let name = match decode_name(file_name, options.non_utf8_names) {
+        Some(name) => name,
+        None => return WalkEntry::warning(&path, "name is not valid UTF-8"),
+    };
+
+    let metadata = match metadata {
+        None => {
+            debug!("Skipping symlink {:?}", path);
+            return WalkEntry::Skipped;
+        }
+        Some(Ok(metadata)) => metadata,
+        Some(Err(e)) => return WalkEntry::warning(&path, e),
+    };
+
+    if options.is_skipped(name.as_str(), metadata.is_dir()) {
+        debug!("Skipping hidden or partial entry {:?}", path);
+        return WalkEntry::Skipped;
+    }
+
+    if ignore.is_ignored(&path, metadata.is_dir()) {
+        debug!("Skipping {:?} matched by {}", path, IGNORE_FILE_NAME);
+        return WalkEntry::Skipped;
+    }
+
+    if !metadata.is_dir() {
+        return WalkEntry::File(LocalFile {
+            path,
+            size: metadata.len(),
+            name,
+        });
+    }
+
+    if is_symlink && options.symlinks == SymlinkPolicy::FilesOnly {
+        debug!("Skipping symlinked directory {:?}", path);
+        return WalkEntry::Skipped;
+    }
+
+    if !options.should_descend(name.as_str(), depth) {
+        return WalkEntry::Skipped;
+    }
+
+    match directory_key(&path, &metadata) {
+        Ok(key) => WalkEntry::Directory(path, key),
+        Err(e) => WalkEntry::warning(&path, e),
+    }
+}
+
+/// Adds the rules of the [IGNORE_FILE_NAME] file in the folder, a missing file is fine but one that can't be read or parsed is returned as warning
+pub(crate) fn read_ignore_rules(
+    folder_path: &Path,
+    ignore: IgnoreRules,
+    options: &WalkOptions,
+) -> (IgnoreRules, Option<WalkWarning>) {
+    if options.no_ignore_files {
+        return (ignore, None);
+    }
+
+    match ignore.with_folder(folder_path) {
+        Ok(rules) => (rules, None),
+        Err(e) => {
+            let warning = WalkWarning::new(&folder_path.join(IGNORE_FILE_NAME), e);
+
+            (ignore, Some(warning))
+        }
+    }
+}
+
+impl<'a> Walker<'a> {
+    fn warn(&mut self, path: &Path, message: impl ToString) {
+        self.result.warnings.push(WalkWarning::new(path, message));
+    }
+
+    #[async_recursion]
+    async fn walk(
+        &mut self,
+        folder_path: &PathBuf,
+        depth: usize,
+        ignore: IgnoreRules,
+    ) -> anyhow::Result<()> {
+        let options = self.options.clone();
+        let path = folder_path.clone();
+
+        let (ignore, warning) =
+            tokio::task::spawn_blocking(move || read_ignore_rules(&path, ignore, &options)).await?;
+
+        if let Some(warning) = warning {
+            self.result.warnings.push(warning);
+        }
 
-        if path.is_dir() {
-            files.append(&mut read_files_recursive(&path).await?);
-        } else {
-            let metadata = entry.metadata().await?;
+        let mut dir = fs::read_dir(&folder_path).await?;
 
-            let file = LocalFile {
-                path: path.clone(),
-                size: metadata.len(),
-                name: path.file_name().unwrap().to_str().unwrap().to_string(),
+        loop {
+            let entry = match dir.next_entry().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(e) => {
+                    self.warn(folder_path, e);
+                    break;
+                }
             };
 
-            files.push(file);
+            match read_entry(&entry, self.options, &ignore, depth).await {
+                WalkEntry::File(file) => self.result.files.push(file),
+                WalkEntry::Directory(path, key) => {
+                    if !self.visited.insert(key) {
+                        self.warn(&path, DIRECTORY_VISITED);
+                        continue;
+                    }
+
+                    if let Err(e) = self.walk(&path, depth + 1, ignore.clone()).await {
+                        self.warn(&path, e);
+                    }
+                }
+                WalkEntry::Skipped => {}
+                WalkEntry::Warning(warning) => self.result.warnings.push(warning),
+            }
         }
+
+        Ok(())
     }
+}
+
+#[cfg(unix)]
+pub(crate) fn directory_key(_path: &Path, metadata: &Metadata) -> anyhow::Result<DirectoryKey> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok((metadata.dev(), metadata.ino()))
+}
 
-    return Ok(files);
+#[cfg(not(unix))]
+pub(crate) fn directory_key(path: &Path, _metadata: &Metadata) -> anyhow::Result<DirectoryKey> {
+    Ok(std::fs::canonicalize(path)?)
+}
+
+fn decode_name(name: &OsStr, policy: NonUtf8Policy) -> Option<String> {
+    if let Some(name) = name.to_str() {
+        return Some(name.to_string());
+    }
+
+    match policy {
+        NonUtf8Policy::Lossy => Some(name.to_string_lossy().to_string()),
+        NonUtf8Policy::PercentEncode => Some(percent_encode_invalid(name.as_encoded_bytes())),
+        NonUtf8Policy::Skip => None,
+    }
+}
+
+fn percent_encode_invalid(mut bytes: &[u8]) -> String {
+    let mut name = String::new();
+
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                name.push_str(valid);
+                return name;
+            }
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                let invalid_length = e.error_len().unwrap_or(rest.len());
+
+                // valid_up_to guarantees this part is valid UTF-8
+                name.push_str(std::str::from_utf8(valid).unwrap_or_default());
+
+                for byte in &rest[..invalid_length] {
+                    name.push_str(format!("%{:02X}", byte).as_str());
+                }
+
+                bytes = &rest[invalid_length..];
+            }
+        }
+    }
 }
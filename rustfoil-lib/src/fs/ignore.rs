@@ -0,0 +1,50 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Name of the gitignore-style file which hides entries of the folder it's in & all subfolders
+pub const IGNORE_FILE_NAME: &str = ".rustfoilignore";
+
+/// Ignore files found on the way from the scanned folder down to the current one
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    matchers: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Adds the rules of an ignore file found in `folder`, which take precedence over the ones of its parents
+    pub fn with_file(&self, folder: &Path, content: &str) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new(folder);
+
+        for line in content.lines() {
+            builder.add_line(None, line)?;
+        }
+
+        let mut matchers = self.matchers.clone();
+        matchers.push(Arc::new(builder.build()?));
+
+        Ok(Self { matchers })
+    }
+
+    /// Reads the ignore file of `folder` if there is one
+    pub fn with_folder(&self, folder: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(folder.join(IGNORE_FILE_NAME)) {
+            Ok(content) => self.with_file(folder, content.as_str()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(self.clone()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in self.matchers.iter().rev() {
+            match matcher.matched(path, is_dir) {
+                Match::None => continue,
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+            }
+        }
+
+        false
+    }
+}
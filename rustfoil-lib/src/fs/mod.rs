@@ -1 +1,7 @@
+pub mod container;
 pub mod file;
+pub mod ignore;
+pub mod partition;
+pub mod stream;
+pub mod verify;
+pub mod xci;
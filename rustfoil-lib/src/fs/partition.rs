@@ -0,0 +1,371 @@
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+pub const PFS0_MAGIC: &[u8; 4] = b"PFS0";
+pub const HFS0_MAGIC: &[u8; 4] = b"HFS0";
+
+const HEADER_LENGTH: usize = 0x10;
+
+/// Upper bounds to not allocate huge buffers for corrupt headers, real partitions have a handful of entries
+const MAX_ENTRIES: usize = 0x1000;
+const MAX_STRING_TABLE_LENGTH: usize = 0x10000;
+
+/// Both formats share their layout, HFS0 entries additionally carry a hash of the entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionFormat {
+    /// Used by NSP & NSZ files
+    Pfs0,
+    /// Used by the partitions of XCI & XCZ files
+    Hfs0,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionEntry {
+    pub name: String,
+    /// Offset relative to the start of the data section
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Entry table of a PFS0 or HFS0 partition, neither the header nor the names are encrypted
+#[derive(Debug, Clone)]
+pub struct PartitionHeader {
+    pub format: PartitionFormat,
+    pub entries: Vec<PartitionEntry>,
+    /// Offset of the data section relative to the start of the partition, i.e. the length of the header
+    pub data_offset: u64,
+}
+
+impl PartitionFormat {
+    pub fn magic(&self) -> &'static [u8; 4] {
+        match self {
+            PartitionFormat::Pfs0 => PFS0_MAGIC,
+            PartitionFormat::Hfs0 => HFS0_MAGIC,
+        }
+    }
+
+    fn entry_length(&self) -> usize {
+        match self {
+            PartitionFormat::Pfs0 => 0x18,
+            PartitionFormat::Hfs0 => 0x40,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PartitionFormat::Pfs0 => "PFS0",
+            PartitionFormat::Hfs0 => "HFS0",
+        }
+    }
+}
+
+impl PartitionHeader {
+    pub fn parse(format: PartitionFormat, bytes: &[u8]) -> anyhow::Result<PartitionHeader> {
+        let (entry_count, string_table_length) = parse_counts(format, bytes)?;
+        let header_length = header_length(format, entry_count, string_table_length);
+
+        if bytes.len() < header_length {
+            return Err(anyhow::anyhow!(
+                "{} header is truncated, expected {} bytes but got {}",
+                format.name(),
+                header_length,
+                bytes.len()
+            ));
+        }
+
+        let string_table = &bytes[header_length - string_table_length..header_length];
+        let entry_length = format.entry_length();
+
+        let entries = (0..entry_count)
+            .map(|index| {
+                let entry = &bytes[HEADER_LENGTH + index * entry_length..][..entry_length];
+                let name_offset = read_u32(entry, 0x10) as usize;
+
+                let name = string_table
+                    .get(name_offset..)
+                    .map(|name| {
+                        let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+                        String::from_utf8_lossy(&name[..end]).to_string()
+                    })
+                    .ok_or(anyhow::anyhow!(
+                        "{} entry {} has an invalid name offset",
+                        format.name(),
+                        index
+                    ))?;
+
+                Ok(PartitionEntry {
+                    name,
+                    offset: read_u64(entry, 0x0),
+                    size: read_u64(entry, 0x8),
+                })
+            })
+            .collect::<anyhow::Result<Vec<PartitionEntry>>>()?;
+
+        Ok(PartitionHeader {
+            format,
+            entries,
+            data_offset: header_length as u64,
+        })
+    }
+
+    /// Reads the header of a partition starting at `offset`
+    pub async fn read(
+        format: PartitionFormat,
+        file: &mut File,
+        offset: u64,
+    ) -> anyhow::Result<PartitionHeader> {
+        let mut bytes = vec![0u8; HEADER_LENGTH];
+
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut bytes).await?;
+
+        let (entry_count, string_table_length) = parse_counts(format, &bytes)?;
+
+        bytes.resize(header_length(format, entry_count, string_table_length), 0);
+        file.read_exact(&mut bytes[HEADER_LENGTH..]).await?;
+
+        Self::parse(format, &bytes)
+    }
+
+    /// Total length of the partition as described by the header
+    pub fn length(&self) -> anyhow::Result<u64> {
+        let mut data_length = 0;
+
+        for entry in &self.entries {
+            data_length = data_length.max(checked_sum(&[entry.offset, entry.size])?);
+        }
+
+        checked_sum(&[self.data_offset, data_length])
+    }
+
+    /// Absolute offset of an entry of this partition, which starts at `offset`
+    pub fn entry_offset(&self, offset: u64, entry: &PartitionEntry) -> anyhow::Result<u64> {
+        checked_sum(&[offset, self.data_offset, entry.offset])
+    }
+
+    pub fn find(&self, suffix: &str) -> Option<&PartitionEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name.ends_with(suffix))
+    }
+}
+
+fn parse_counts(format: PartitionFormat, bytes: &[u8]) -> anyhow::Result<(usize, usize)> {
+    if bytes.len() < HEADER_LENGTH || &bytes[..4] != format.magic() {
+        return Err(anyhow::anyhow!(
+            "Not a {} partition, magic is missing",
+            format.name()
+        ));
+    }
+
+    let entry_count = read_u32(bytes, 0x4) as usize;
+    let string_table_length = read_u32(bytes, 0x8) as usize;
+
+    if entry_count > MAX_ENTRIES || string_table_length > MAX_STRING_TABLE_LENGTH {
+        return Err(anyhow::anyhow!(
+            "{} header is implausible, {} entries with a {} byte string table",
+            format.name(),
+            entry_count,
+            string_table_length
+        ));
+    }
+
+    Ok((entry_count, string_table_length))
+}
+
+fn header_length(format: PartitionFormat, entry_count: usize, string_table_length: usize) -> usize {
+    HEADER_LENGTH + entry_count * format.entry_length() + string_table_length
+}
+
+/// Adds offsets & sizes read from a header, corrupt headers may declare values which overflow
+pub(crate) fn checked_sum(values: &[u64]) -> anyhow::Result<u64> {
+    values
+        .iter()
+        .try_fold(0u64, |sum, value| sum.checked_add(*value))
+        .ok_or(anyhow::anyhow!(
+            "Header declares offsets beyond the largest possible file"
+        ))
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a partition header with the entries laid out back to back in the data section
+    pub(crate) fn partition_bytes(format: PartitionFormat, entries: &[(&str, u64)]) -> Vec<u8> {
+        let mut string_table = Vec::new();
+        let mut table = Vec::new();
+        let mut offset = 0u64;
+
+        for (name, size) in entries {
+            let mut entry = vec![0u8; format.entry_length()];
+            entry[0x0..0x8].copy_from_slice(&offset.to_le_bytes());
+            entry[0x8..0x10].copy_from_slice(&size.to_le_bytes());
+            entry[0x10..0x14].copy_from_slice(&(string_table.len() as u32).to_le_bytes());
+            table.extend(entry);
+
+            string_table.extend(name.as_bytes());
+            string_table.push(0);
+            offset += size;
+        }
+
+        // real headers pad the string table, the names are NUL terminated
+        string_table.resize(string_table.len().next_multiple_of(0x10), 0);
+
+        let mut bytes = format.magic().to_vec();
+        bytes.extend((entries.len() as u32).to_le_bytes());
+        bytes.extend((string_table.len() as u32).to_le_bytes());
+        bytes.extend([0u8; 4]);
+        bytes.extend(table);
+        bytes.extend(string_table);
+        bytes
+    }
+
+    #[test]
+    fn parses_pfs0_headers() {
+        let bytes = partition_bytes(
+            PartitionFormat::Pfs0,
+            &[
+                ("a.nca", 0x100),
+                ("0100abcd000100000000000000000005.tik", 0x2C0),
+            ],
+        );
+        let header = PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).unwrap();
+
+        assert_eq!(header.data_offset, bytes.len() as u64);
+        assert_eq!(header.entries.len(), 2);
+        assert_eq!(header.entries[0].name, "a.nca");
+        assert_eq!(header.entries[1].offset, 0x100);
+        assert_eq!(header.entries[1].size, 0x2C0);
+        assert_eq!(header.length().unwrap(), bytes.len() as u64 + 0x3C0);
+
+        let ticket = header.find(".tik").unwrap();
+        assert_eq!(
+            header.entry_offset(0x1000, ticket).unwrap(),
+            0x1000 + bytes.len() as u64 + 0x100
+        );
+        assert!(header.find(".cnmt.xml").is_none());
+    }
+
+    #[test]
+    fn parses_hfs0_headers() {
+        let bytes = partition_bytes(PartitionFormat::Hfs0, &[("secure", 0x1000)]);
+        let header = PartitionHeader::parse(PartitionFormat::Hfs0, &bytes).unwrap();
+
+        assert_eq!(header.format, PartitionFormat::Hfs0);
+        assert_eq!(header.entries[0].name, "secure");
+        assert_eq!(header.data_offset, (HEADER_LENGTH + 0x40 + 0x10) as u64);
+
+        // the same bytes aren't a PFS0 partition
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).is_err());
+    }
+
+    #[test]
+    fn parses_empty_partitions() {
+        let bytes = partition_bytes(PartitionFormat::Pfs0, &[]);
+        let header = PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).unwrap();
+
+        assert!(header.entries.is_empty());
+        assert_eq!(header.length().unwrap(), HEADER_LENGTH as u64);
+    }
+
+    #[test]
+    fn rejects_missing_magic() {
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &[]).is_err());
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, b"PFS0").is_err());
+
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10)]);
+        bytes[0] = b'X';
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10), ("b.nca", 0x10)]);
+
+        for length in [HEADER_LENGTH, HEADER_LENGTH + 0x18, bytes.len() - 1] {
+            let error =
+                PartitionHeader::parse(PartitionFormat::Pfs0, &bytes[..length]).unwrap_err();
+            assert!(error.to_string().contains("truncated"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn rejects_implausible_counts() {
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10)]);
+        bytes[0x4..0x8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).is_err());
+
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10)]);
+        bytes[0x8..0xC].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_name_offsets_outside_the_string_table() {
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10)]);
+        bytes[HEADER_LENGTH + 0x10..HEADER_LENGTH + 0x14].copy_from_slice(&0x11u32.to_le_bytes());
+
+        assert!(PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).is_err());
+    }
+
+    #[test]
+    fn reads_unterminated_names_to_the_end_of_the_string_table() {
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("0123456789abcde", 0x10)]);
+        let last = bytes.len() - 1;
+        bytes[last] = b'f';
+
+        let header = PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).unwrap();
+        assert_eq!(header.entries[0].name, "0123456789abcdef");
+    }
+
+    #[test]
+    fn rejects_offsets_that_overflow() {
+        let mut bytes = partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x10)]);
+        bytes[HEADER_LENGTH..HEADER_LENGTH + 0x8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let header = PartitionHeader::parse(PartitionFormat::Pfs0, &bytes).unwrap();
+        assert!(header.length().is_err());
+        assert!(header.entry_offset(0, &header.entries[0]).is_err());
+
+        assert!(checked_sum(&[u64::MAX - 1, 1]).is_ok());
+        assert!(checked_sum(&[u64::MAX, 1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_headers_at_an_offset() {
+        let path = std::env::temp_dir().join(format!("rustfoil-partition-{}", std::process::id()));
+        let header = partition_bytes(PartitionFormat::Hfs0, &[("normal", 0x10), ("secure", 0x20)]);
+
+        let mut bytes = vec![0xFFu8; 0x200];
+        bytes.extend(&header);
+        tokio::fs::write(&path, &bytes).await.unwrap();
+
+        let mut file = File::open(&path).await.unwrap();
+        let read = PartitionHeader::read(PartitionFormat::Hfs0, &mut file, 0x200)
+            .await
+            .unwrap();
+        assert_eq!(read.entries[1].name, "secure");
+
+        // the header claims more bytes than the file has
+        tokio::fs::write(&path, &bytes[..bytes.len() - 1])
+            .await
+            .unwrap();
+        let mut file = File::open(&path).await.unwrap();
+        assert!(
+            PartitionHeader::read(PartitionFormat::Hfs0, &mut file, 0x200)
+                .await
+                .is_err()
+        );
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
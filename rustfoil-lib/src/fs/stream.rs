@@ -0,0 +1,133 @@
+use crate::fs::file::{directory_key, read_entry_blocking, LocalFile, WalkEntry, WalkOptions};
+use crate::fs::file::WalkWarning;
+use crate::fs::file::{read_ignore_rules, DirectoryKey, DIRECTORY_VISITED};
+use crate::fs::ignore::IgnoreRules;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+/// Number of files buffered before workers wait for the consumer
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Shared between all directory workers, the walk ends once the last sender is dropped
+#[derive(Clone)]
+struct StreamWalker {
+    options: Arc<WalkOptions>,
+    visited: Arc<Mutex<HashSet<DirectoryKey>>>,
+    permits: Arc<Semaphore>,
+    sender: mpsc::Sender<Result<LocalFile, WalkWarning>>,
+}
+
+/// Walks the folder with up to `workers` directories being read at once, yielding files as soon as they're found.
+///
+/// Unlike [crate::fs::file::read_files] the order of the files isn't stable between runs.
+pub async fn stream_files(
+    folder_path: &PathBuf,
+    options: WalkOptions,
+    workers: usize,
+) -> anyhow::Result<impl Stream<Item = Result<LocalFile, WalkWarning>>> {
+    let metadata = fs::metadata(folder_path).await?;
+
+    if !metadata.is_dir() {
+        return Err(anyhow::anyhow!("{:?} is not a directory", folder_path));
+    }
+
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+
+    let walker = StreamWalker {
+        options: Arc::new(options),
+        visited: Arc::new(Mutex::new(HashSet::new())),
+        permits: Arc::new(Semaphore::new(workers.max(1))),
+        sender,
+    };
+
+    walker.mark_visited(directory_key(folder_path, &metadata)?);
+    walker.spawn(folder_path.clone(), 0, IgnoreRules::default());
+
+    Ok(ReceiverStream::new(receiver))
+}
+
+impl StreamWalker {
+    fn mark_visited(&self, key: DirectoryKey) -> bool {
+        match self.visited.lock() {
+            Ok(mut visited) => visited.insert(key),
+            Err(poisoned) => poisoned.into_inner().insert(key),
+        }
+    }
+
+    fn spawn(self, folder_path: PathBuf, depth: usize, ignore: IgnoreRules) {
+        tokio::spawn(async move {
+            let permit = match self.permits.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            };
+
+            if let Err(e) = self.walk(&folder_path, depth, ignore).await {
+                let _ = self
+                    .sender
+                    .send(Err(WalkWarning::new(&folder_path, e)))
+                    .await;
+            }
+
+            drop(permit);
+        });
+    }
+
+    async fn walk(
+        &self,
+        folder_path: &Path,
+        depth: usize,
+        ignore: IgnoreRules,
+    ) -> anyhow::Result<()> {
+        let options = self.options.clone();
+        let path = folder_path.to_path_buf();
+
+        // one blocking task per directory instead of one per entry, see benches/walk.rs
+        let (entries, ignore, warning) = tokio::task::spawn_blocking(move || {
+            let (ignore, warning) = read_ignore_rules(&path, ignore, &options);
+
+            let mut entries = Vec::new();
+
+            for entry in std::fs::read_dir(&path)? {
+                // a single unreadable entry doesn't hide the rest of the directory
+                entries.push(match entry {
+                    Ok(entry) => read_entry_blocking(&entry, &options, &ignore, depth),
+                    Err(e) => WalkEntry::warning(&path, e),
+                });
+            }
+
+            Ok::<_, std::io::Error>((entries, ignore, warning))
+        })
+        .await??;
+
+        if let Some(warning) = warning {
+            let _ = self.sender.send(Err(warning)).await;
+        }
+
+        for entry in entries {
+            let item = match entry {
+                WalkEntry::File(file) => Ok(file),
+                WalkEntry::Directory(path, key) => match self.mark_visited(key) {
+                    true => {
+                        self.clone().spawn(path, depth + 1, ignore.clone());
+                        continue;
+                    }
+                    false => Err(WalkWarning::new(&path, DIRECTORY_VISITED)),
+                },
+                WalkEntry::Skipped => continue,
+                WalkEntry::Warning(warning) => Err(warning),
+            };
+
+            // the consumer dropped the stream, no need to keep walking
+            if self.sender.send(item).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
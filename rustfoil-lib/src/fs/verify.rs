@@ -0,0 +1,265 @@
+use crate::fs::container::{PFS0_EXTENSIONS, XCI_EXTENSIONS};
+use crate::fs::partition::{checked_sum, PartitionFormat, PartitionHeader};
+use crate::fs::xci::XciHeader;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum VerifyStatus {
+    Ok,
+    /// The file is shorter than its header declares, e.g. an unfinished upload
+    Truncated,
+    /// The file is longer than its header declares, XCIs may be padded up to their cartridge capacity
+    Padded,
+    BadMagic,
+    /// The header itself couldn't be parsed
+    Corrupt {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    pub path: String,
+    pub size: u64,
+    /// Size declared by the container header, if it could be read
+    pub expected_size: Option<u64>,
+    /// Largest size still accepted, only differs from the expected size for XCIs
+    pub max_size: Option<u64>,
+    #[serde(flatten)]
+    pub status: VerifyStatus,
+}
+
+impl Display for VerifyStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyStatus::Ok => write!(f, "ok"),
+            VerifyStatus::Truncated => write!(f, "truncated"),
+            VerifyStatus::Padded => write!(f, "padded"),
+            VerifyStatus::BadMagic => write!(f, "bad magic"),
+            VerifyStatus::Corrupt { message } => write!(f, "corrupt: {}", message),
+        }
+    }
+}
+
+impl VerifyResult {
+    pub fn is_ok(&self) -> bool {
+        self.status == VerifyStatus::Ok
+    }
+}
+
+impl Display for VerifyResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.expected_size {
+            Some(expected) if self.status != VerifyStatus::Ok => write!(
+                f,
+                "{}, expected {} bytes but is {}",
+                self.status, expected, self.size
+            ),
+            _ => write!(f, "{}", self.status),
+        }
+    }
+}
+
+/// Compares the size declared by the PFS0/HFS0 tables of a local NSP/NSZ/XCI/XCZ with its real size,
+/// files ending inside their headers are truncated as well, returns `None` for other files
+pub async fn verify_file(path: &Path) -> anyhow::Result<Option<VerifyResult>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let is_xci = XCI_EXTENSIONS.contains(&extension.as_str());
+
+    if !is_xci && !PFS0_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(None);
+    }
+
+    let mut file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+
+    let declared = match is_xci {
+        true => read_xci_size(&mut file).await,
+        false => read_pfs0_size(&mut file).await,
+    };
+
+    let (expected_size, max_size, status) = match declared {
+        Ok((expected, max)) => (
+            Some(expected),
+            Some(max),
+            match size {
+                size if size < expected => VerifyStatus::Truncated,
+                size if size > max => VerifyStatus::Padded,
+                _ => VerifyStatus::Ok,
+            },
+        ),
+        Err(status) => (None, None, status),
+    };
+
+    Ok(Some(VerifyResult {
+        path: path.to_string_lossy().to_string(),
+        size,
+        expected_size,
+        max_size,
+        status,
+    }))
+}
+
+async fn read_pfs0_size(file: &mut File) -> Result<(u64, u64), VerifyStatus> {
+    let mut magic = [0u8; 4];
+
+    if file.read_exact(&mut magic).await.is_err() || &magic != PartitionFormat::Pfs0.magic() {
+        return Err(VerifyStatus::BadMagic);
+    }
+
+    let header = PartitionHeader::read(PartitionFormat::Pfs0, file, 0)
+        .await
+        .map_err(header_error)?;
+
+    let length = header.length().map_err(corrupt)?;
+
+    Ok((length, length))
+}
+
+async fn read_xci_size(file: &mut File) -> Result<(u64, u64), VerifyStatus> {
+    if !XciHeader::has_magic(file).await.map_err(corrupt)? {
+        return Err(VerifyStatus::BadMagic);
+    }
+
+    let xci = XciHeader::read(file).await.map_err(header_error)?;
+    let root_length = xci.root.length().map_err(corrupt)?;
+    let root_end = checked_sum(&[xci.root_offset, root_length]).map_err(corrupt)?;
+
+    // trimmed dumps end at the valid data end, which also covers data after the partitions
+    let expected = root_end.max(xci.valid_data_end);
+
+    let max = match xci.cartridge_capacity {
+        None => expected,
+        Some(capacity) => {
+            expected.max(checked_sum(&[xci.key_area_length, capacity]).map_err(corrupt)?)
+        }
+    };
+
+    Ok((expected, max))
+}
+
+fn corrupt(e: anyhow::Error) -> VerifyStatus {
+    VerifyStatus::Corrupt {
+        message: format!("{:#}", e),
+    }
+}
+
+/// Files ending inside a header are cut off rather than corrupt
+fn header_error(e: anyhow::Error) -> VerifyStatus {
+    let ended_early = e.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+    });
+
+    match ended_early {
+        true => VerifyStatus::Truncated,
+        false => corrupt(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::partition::tests::partition_bytes;
+    use crate::fs::xci::tests::xci_bytes;
+
+    async fn verify(name: &str, bytes: &[u8]) -> VerifyResult {
+        let path =
+            std::env::temp_dir().join(format!("rustfoil-verify-{}-{}", std::process::id(), name));
+        tokio::fs::write(&path, bytes).await.unwrap();
+
+        let result = verify_file(&path).await.unwrap().unwrap();
+
+        tokio::fs::remove_file(&path).await.unwrap();
+        result
+    }
+
+    fn nsp_bytes() -> Vec<u8> {
+        let mut bytes =
+            partition_bytes(PartitionFormat::Pfs0, &[("a.nca", 0x100), ("b.tik", 0x40)]);
+        bytes.extend([0u8; 0x140]);
+        bytes
+    }
+
+    #[tokio::test]
+    async fn verifies_nsp_sizes() {
+        let bytes = nsp_bytes();
+
+        let ok = verify("ok.nsp", &bytes).await;
+        assert_eq!(ok.status, VerifyStatus::Ok);
+        assert_eq!(ok.expected_size, Some(bytes.len() as u64));
+
+        let truncated = verify("truncated.nsp", &bytes[..bytes.len() - 1]).await;
+        assert_eq!(truncated.status, VerifyStatus::Truncated);
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(
+            verify("padded.nsp", &padded).await.status,
+            VerifyStatus::Padded
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_files_ending_inside_the_header_as_truncated() {
+        let bytes = nsp_bytes();
+
+        for length in [0x8, 0x20, 0x4F] {
+            let name = format!("header-{}.nsp", length);
+            let result = verify(name.as_str(), &bytes[..length]).await;
+
+            assert_eq!(result.status, VerifyStatus::Truncated, "{}", length);
+            assert_eq!(result.expected_size, None);
+        }
+
+        let xci = xci_bytes(false);
+        let result = verify("header.xci", &xci[..0x220]).await;
+        assert_eq!(result.status, VerifyStatus::Truncated);
+    }
+
+    #[tokio::test]
+    async fn reports_bad_magic_and_corrupt_headers() {
+        let mut bytes = nsp_bytes();
+        bytes[0] = b'X';
+        assert_eq!(
+            verify("magic.nsp", &bytes).await.status,
+            VerifyStatus::BadMagic
+        );
+        assert_eq!(
+            verify("empty.nsp", &[]).await.status,
+            VerifyStatus::BadMagic
+        );
+
+        let mut bytes = nsp_bytes();
+        bytes[0x4..0x8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            verify("counts.nsp", &bytes).await.status,
+            VerifyStatus::Corrupt { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn checks_xcis_against_their_valid_data_end() {
+        let mut bytes = xci_bytes(false);
+        bytes.resize(0x1000, 0);
+
+        let ok = verify("ok.xci", &bytes).await;
+        assert_eq!(ok.status, VerifyStatus::Ok);
+        assert_eq!(ok.expected_size, Some(0x1000));
+        assert_eq!(ok.max_size, Some(4 * 1024 * 1024 * 1024));
+
+        // the partitions are complete, but the data after them is missing
+        let result = verify("trimmed.xci", &bytes[..0x800]).await;
+        assert_eq!(result.status, VerifyStatus::Truncated);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::fs::partition::{checked_sum, read_u64, PartitionFormat, PartitionHeader};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+pub const XCI_MAGIC: &[u8; 4] = b"HEAD";
+
+/// Offset of the magic, the header starts with a 0x100 byte signature
+const MAGIC_OFFSET: u64 = 0x100;
+/// Some dumps are prefixed with the cartridge key area
+const KEY_AREA_LENGTH: u64 = 0x1000;
+const HEADER_LENGTH: usize = 0x200;
+/// Sizes in the header are counted in media units
+const MEDIA_UNIT: u64 = 0x200;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Header of a cartridge image as used by XCI & XCZ files, only the parts needed to find the partitions
+#[derive(Debug, Clone)]
+pub struct XciHeader {
+    /// Length of the key area in front of the header, if the dump has one
+    pub key_area_length: u64,
+    /// Raw cartridge size byte at 0x10D
+    pub cartridge_size_byte: u8,
+    /// Capacity of the cartridge in bytes, `None` for unknown size bytes
+    pub cartridge_capacity: Option<u64>,
+    /// End of the data written to the cartridge, anything after it is padding
+    pub valid_data_end: u64,
+    /// Absolute offset of the root HFS0 partition
+    pub root_offset: u64,
+    pub root: PartitionHeader,
+}
+
+impl XciHeader {
+    /// Checks for the magic with & without a key area in front of the header
+    pub async fn has_magic(file: &mut File) -> anyhow::Result<bool> {
+        Ok(read_key_area_length(file).await?.is_some())
+    }
+
+    pub async fn read(file: &mut File) -> anyhow::Result<XciHeader> {
+        let key_area_length = read_key_area_length(file)
+            .await?
+            .ok_or(anyhow::anyhow!("Not an XCI file, HEAD magic is missing"))?;
+
+        let mut bytes = vec![0u8; HEADER_LENGTH];
+
+        file.seek(SeekFrom::Start(key_area_length)).await?;
+        file.read_exact(&mut bytes).await?;
+
+        let cartridge_size_byte = bytes[0x10D];
+        let root_offset = checked_sum(&[key_area_length, read_u64(&bytes, 0x130)])?;
+
+        let valid_data_end = read_u64(&bytes, 0x118)
+            .checked_add(1)
+            .and_then(|units| units.checked_mul(MEDIA_UNIT))
+            .and_then(|end| end.checked_add(key_area_length))
+            .ok_or(anyhow::anyhow!(
+                "XCI header declares a valid data end beyond the largest possible file"
+            ))?;
+
+        let root = PartitionHeader::read(PartitionFormat::Hfs0, file, root_offset)
+            .await
+            .map_err(|e| e.context("Invalid root partition"))?;
+
+        Ok(XciHeader {
+            key_area_length,
+            cartridge_size_byte,
+            cartridge_capacity: cartridge_capacity(cartridge_size_byte),
+            valid_data_end,
+            root_offset,
+            root,
+        })
+    }
+
+    /// Absolute offset of a partition in the root partition, e.g. `secure`, `normal` or `update`
+    pub fn partition_offset(&self, name: &str) -> anyhow::Result<Option<u64>> {
+        match self.root.entries.iter().find(|entry| entry.name == name) {
+            None => Ok(None),
+            Some(entry) => Ok(Some(self.root.entry_offset(self.root_offset, entry)?)),
+        }
+    }
+
+    /// Reads the secure partition which holds the NCAs, tickets & certificates of the titles on the cartridge
+    pub async fn read_secure_partition(
+        &self,
+        file: &mut File,
+    ) -> anyhow::Result<(u64, PartitionHeader)> {
+        let offset = self
+            .partition_offset("secure")?
+            .ok_or(anyhow::anyhow!("Root partition has no secure partition"))?;
+
+        let header = PartitionHeader::read(PartitionFormat::Hfs0, file, offset)
+            .await
+            .map_err(|e| e.context("Invalid secure partition"))?;
+
+        Ok((offset, header))
+    }
+}
+
+/// Maps the cartridge size byte at 0x10D to the capacity of the cartridge
+pub fn cartridge_capacity(size_byte: u8) -> Option<u64> {
+    match size_byte {
+        0xFA => Some(GIB),
+        0xF8 => Some(2 * GIB),
+        0xF0 => Some(4 * GIB),
+        0xE0 => Some(8 * GIB),
+        0xE1 => Some(16 * GIB),
+        0xE2 => Some(32 * GIB),
+        _ => None,
+    }
+}
+
+async fn read_key_area_length(file: &mut File) -> anyhow::Result<Option<u64>> {
+    if read_magic(file, MAGIC_OFFSET).await? {
+        return Ok(Some(0));
+    }
+
+    match read_magic(file, KEY_AREA_LENGTH + MAGIC_OFFSET).await? {
+        true => Ok(Some(KEY_AREA_LENGTH)),
+        false => Ok(None),
+    }
+}
+
+async fn read_magic(file: &mut File, offset: u64) -> anyhow::Result<bool> {
+    let mut magic = [0u8; 4];
+
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    match file.read_exact(&mut magic).await {
+        Ok(_) => Ok(&magic == XCI_MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::fs::container::read_title_info;
+    use crate::fs::partition::tests::partition_bytes;
+    use std::path::PathBuf;
+
+    const TICKET: &str = "0100abcd000100000000000000000005.tik";
+
+    /// A 4 GiB cartridge whose root partition holds a secure partition with a ticket, the valid data ends at 0x1000
+    pub(crate) fn xci_bytes(key_area: bool) -> Vec<u8> {
+        let secure = partition_bytes(PartitionFormat::Hfs0, &[(TICKET, 0x2C0)]);
+        let root = partition_bytes(
+            PartitionFormat::Hfs0,
+            &[("secure", secure.len() as u64 + 0x2C0)],
+        );
+
+        let mut header = vec![0u8; HEADER_LENGTH];
+        header[0x100..0x104].copy_from_slice(XCI_MAGIC);
+        header[0x10D] = 0xF0;
+        header[0x118..0x120].copy_from_slice(&0x7u64.to_le_bytes());
+        header[0x130..0x138].copy_from_slice(&(HEADER_LENGTH as u64).to_le_bytes());
+
+        let mut bytes = match key_area {
+            true => vec![0u8; KEY_AREA_LENGTH as usize],
+            false => Vec::new(),
+        };
+        bytes.extend(header);
+        bytes.extend(root);
+        bytes.extend(secure);
+        bytes.extend([0u8; 0x2C0]);
+        bytes
+    }
+
+    async fn write(name: &str, bytes: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("rustfoil-xci-{}-{}.xci", std::process::id(), name));
+        tokio::fs::write(&path, bytes).await.unwrap();
+        path
+    }
+
+    async fn read(name: &str, bytes: &[u8]) -> anyhow::Result<XciHeader> {
+        let path = write(name, bytes).await;
+        let mut file = File::open(&path).await.unwrap();
+        let header = XciHeader::read(&mut file).await;
+
+        tokio::fs::remove_file(&path).await.unwrap();
+        header
+    }
+
+    #[tokio::test]
+    async fn reads_headers_and_the_secure_partition() {
+        for key_area in [false, true] {
+            let name = format!("valid-{}", key_area);
+            let path = write(name.as_str(), &xci_bytes(key_area)).await;
+            let mut file = File::open(&path).await.unwrap();
+
+            assert!(XciHeader::has_magic(&mut file).await.unwrap());
+
+            let header = XciHeader::read(&mut file).await.unwrap();
+            let key_area_length = match key_area {
+                true => KEY_AREA_LENGTH,
+                false => 0,
+            };
+
+            assert_eq!(header.key_area_length, key_area_length);
+            assert_eq!(header.cartridge_capacity, Some(4 * GIB));
+            assert_eq!(header.valid_data_end, key_area_length + 8 * MEDIA_UNIT);
+            assert_eq!(header.root_offset, key_area_length + HEADER_LENGTH as u64);
+            assert_eq!(header.partition_offset("update").unwrap(), None);
+
+            let (offset, secure) = header.read_secure_partition(&mut file).await.unwrap();
+            assert_eq!(Some(offset), header.partition_offset("secure").unwrap());
+            assert_eq!(secure.entries[0].name, TICKET);
+
+            let info = read_title_info(&path).await.unwrap().unwrap();
+            assert_eq!(info.title_id, "0100ABCD00010000");
+
+            tokio::fs::remove_file(&path).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_files_without_magic() {
+        let path = write("short", b"HEAD").await;
+        let mut file = File::open(&path).await.unwrap();
+
+        assert!(!XciHeader::has_magic(&mut file).await.unwrap());
+        assert!(XciHeader::read(&mut file).await.is_err());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let mut bytes = xci_bytes(false);
+        bytes[0x100] = b'X';
+        assert!(read("magic", &bytes).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_images() {
+        let bytes = xci_bytes(false);
+
+        // ends inside the header & inside the root partition
+        for length in [0x180, HEADER_LENGTH + 0x20] {
+            assert!(
+                read(format!("truncated-{}", length).as_str(), &bytes[..length])
+                    .await
+                    .is_err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupt_partitions() {
+        let mut bytes = xci_bytes(false);
+        bytes[HEADER_LENGTH] = b'X';
+
+        let error = read("root", &bytes).await.unwrap_err();
+        assert!(
+            error.to_string().contains("Invalid root partition"),
+            "{}",
+            error
+        );
+
+        // the root partition doesn't contain a secure partition
+        let mut bytes = xci_bytes(false);
+        let name = HEADER_LENGTH + 0x10 + 0x40;
+        bytes[name..name + 6].copy_from_slice(b"normal");
+
+        let path = write("secure", &bytes).await;
+        let mut file = File::open(&path).await.unwrap();
+        let header = XciHeader::read(&mut file).await.unwrap();
+        assert!(header.read_secure_partition(&mut file).await.is_err());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_offsets_that_overflow() {
+        let mut bytes = xci_bytes(true);
+        let header = KEY_AREA_LENGTH as usize;
+        bytes[header + 0x130..header + 0x138].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(read("root-offset", &bytes).await.is_err());
+
+        let mut bytes = xci_bytes(false);
+        bytes[0x118..0x120].copy_from_slice(&(u64::MAX / MEDIA_UNIT).to_le_bytes());
+        assert!(read("valid-data-end", &bytes).await.is_err());
+    }
+
+    #[test]
+    fn maps_cartridge_sizes() {
+        assert_eq!(cartridge_capacity(0xFA), Some(GIB));
+        assert_eq!(cartridge_capacity(0xE2), Some(32 * GIB));
+        assert_eq!(cartridge_capacity(0x00), None);
+    }
+}
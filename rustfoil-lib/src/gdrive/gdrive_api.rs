@@ -1,5 +1,6 @@
 extern crate google_drive3 as drive3;
 
+use crate::fs::ignore::{IgnoreRules, IGNORE_FILE_NAME};
 use crate::gdrive::model::{GoogleDriveFileInfo, GoogleDriveFolderInfo, GoogleDriveScanResult};
 use async_recursion::async_recursion;
 use drive3::{hyper, hyper_rustls, oauth2, DriveHub};
@@ -10,7 +11,7 @@ use google_drive3::hyper::{Body, Response};
 use hyper_rustls::HttpsConnector;
 use oauth2::{InstalledFlowAuthenticator, InstalledFlowReturnMethod};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub struct GoogleDriveApiService {
     drive_hub: DriveHub<HttpsConnector<HttpConnector>>,
@@ -36,7 +37,7 @@ impl GoogleDriveApiService {
         let hub = DriveHub::new(
             hyper::Client::builder().build(
                 hyper_rustls::HttpsConnectorBuilder::new()
-                    .with_native_roots()
+                    .with_native_roots()?
                     .https_or_http()
                     .enable_http1()
                     .build(),
@@ -65,12 +66,26 @@ impl GoogleDriveApiService {
             .get(file_id)
             .supports_all_drives(true)
             .add_scope(Full)
-            .param("fields", "id,name,size,permissionIds")
+            .param("fields", "id,name,size,md5Checksum,permissionIds")
             .doit()
             .await?;
         Ok(file)
     }
 
+    pub async fn download_file(&self, file_id: &str) -> anyhow::Result<Vec<u8>> {
+        let (response, _) = self
+            .drive_hub
+            .files()
+            .get(file_id)
+            .supports_all_drives(true)
+            .add_scope(Full)
+            .param("alt", "media")
+            .doit()
+            .await?;
+
+        Ok(hyper::body::to_bytes(response.into_body()).await?.to_vec())
+    }
+
     pub async fn ls(
         &self,
         folder_id: &str,
@@ -98,7 +113,7 @@ impl GoogleDriveApiService {
                 .include_items_from_all_drives(true)
                 .param(
                     "fields",
-                    "files(id,name,size,permissionIds,shortcutDetails),nextPageToken",
+                    "files(id,name,size,md5Checksum,permissionIds,shortcutDetails),nextPageToken",
                 );
 
             let resp = match page_token {
@@ -228,19 +243,44 @@ impl GoogleDriveApiService {
         &self,
         folder_id: &str,
         recursion: bool,
+        path: &str,
+        ignore: Option<&IgnoreRules>,
     ) -> anyhow::Result<GoogleDriveScanResult> {
         let mut files = Vec::new();
         let mut folders = Vec::new();
 
-        for file in self.lsf(folder_id).await? {
+        let drive_files = self.lsf(folder_id).await?;
+
+        let ignore = match ignore {
+            None => None,
+            Some(rules) => Some(self.read_ignore_file(&drive_files, path, rules).await?),
+        };
+
+        let is_ignored = |path: &str, is_dir: bool| {
+            ignore
+                .as_ref()
+                .is_some_and(|rules| rules.is_ignored(Path::new(path), is_dir))
+        };
+
+        for file in drive_files {
             if let Some(_) = &file.size {
+                let name = file.name.clone().unwrap();
+                let file_path = join_drive_path(path, &name);
+
+                if name == IGNORE_FILE_NAME || is_ignored(file_path.as_str(), false) {
+                    continue;
+                }
+
                 let is_shared = self.is_file_shared(&file).await?;
 
                 files.push(GoogleDriveFileInfo::new(
                     file.id.unwrap(),
                     file.size.unwrap(),
-                    file.name.unwrap(),
+                    name,
                     is_shared,
+                    folder_id.to_string(),
+                    file_path,
+                    file.md5_checksum,
                 ));
             }
         }
@@ -252,6 +292,15 @@ impl GoogleDriveApiService {
                 if let Some(mime_type) = &info.target_mime_type {
                     if mime_type == "application/vnd.google-apps.folder" {
                         if let Some(id) = &info.target_id {
+                            let folder_path = join_drive_path(
+                                path,
+                                shortcut.name.as_deref().unwrap_or(id.as_str()),
+                            );
+
+                            if is_ignored(folder_path.as_str(), true) {
+                                continue;
+                            }
+
                             let folder = self.get_file(id).await?;
 
                             folders.push(GoogleDriveFolderInfo::new(
@@ -259,8 +308,15 @@ impl GoogleDriveApiService {
                                 self.is_file_shared(&folder).await?,
                             ));
 
-                            for file_info in
-                                self.get_all_files_in_folder(id, recursion).await?.files
+                            for file_info in self
+                                .get_all_files_in_folder(
+                                    id,
+                                    recursion,
+                                    folder_path.as_str(),
+                                    ignore.as_ref(),
+                                )
+                                .await?
+                                .files
                             {
                                 files.push(file_info);
                             }
@@ -271,8 +327,20 @@ impl GoogleDriveApiService {
 
             for folder in self.lsd(folder_id).await? {
                 let folder_id = folder.id.to_owned().unwrap();
+                let folder_path =
+                    join_drive_path(path, folder.name.as_deref().unwrap_or(folder_id.as_str()));
+
+                if is_ignored(folder_path.as_str(), true) {
+                    continue;
+                }
+
                 for file_info in self
-                    .get_all_files_in_folder(folder_id.as_str(), recursion)
+                    .get_all_files_in_folder(
+                        folder_id.as_str(),
+                        recursion,
+                        folder_path.as_str(),
+                        ignore.as_ref(),
+                    )
                     .await?
                     .files
                 {
@@ -288,6 +356,29 @@ impl GoogleDriveApiService {
         Ok(GoogleDriveScanResult::new(files, folders))
     }
 
+    /// Adds the rules of the ignore file in the listed folder, if there is one
+    async fn read_ignore_file(
+        &self,
+        files: &[File],
+        path: &str,
+        ignore: &IgnoreRules,
+    ) -> anyhow::Result<IgnoreRules> {
+        let ignore_file = files
+            .iter()
+            .find(|file| file.name.as_deref() == Some(IGNORE_FILE_NAME));
+
+        let id = match ignore_file.and_then(|file| file.id.as_ref()) {
+            None => return Ok(ignore.clone()),
+            Some(id) => id,
+        };
+
+        let content = self.download_file(id.as_str()).await?;
+
+        ignore
+            .with_file(Path::new(path), String::from_utf8_lossy(&content).as_ref())
+            .map_err(|e| anyhow::anyhow!("Invalid {} in {:?}: {}", IGNORE_FILE_NAME, path, e))
+    }
+
     pub async fn share_file(&self, file_id: &str) -> anyhow::Result<(Response<Body>, Permission)> {
         let mut perms = Permission::default();
         perms.role = Option::from("reader".to_string());
@@ -367,12 +458,23 @@ impl GoogleDriveApiService {
         };
 
         let is_shared = self.is_file_shared(&res).await?;
+        let name = res.name.unwrap();
 
         Ok(GoogleDriveFileInfo::new(
             res.id.unwrap(),
             res.size.unwrap(),
-            res.name.unwrap(),
+            name.clone(),
             is_shared,
+            dest_folder_id.clone().unwrap_or("root".to_string()),
+            name,
+            res.md5_checksum,
         ))
     }
 }
+
+fn join_drive_path(parent: &str, name: &str) -> String {
+    match parent.is_empty() {
+        true => name.to_string(),
+        false => format!("{}/{}", parent, name),
+    }
+}
@@ -9,6 +9,9 @@ pub struct GoogleDriveFileInfo {
     pub size: i64,
     pub name: String,
     pub shared: bool,
+    pub folder_id: String,
+    pub path: String,
+    pub md5_checksum: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -50,12 +53,23 @@ impl GoogleDriveFolderInfo {
 }
 
 impl GoogleDriveFileInfo {
-    pub fn new(id: String, size: i64, name: String, shared: bool) -> GoogleDriveFileInfo {
+    pub fn new(
+        id: String,
+        size: i64,
+        name: String,
+        shared: bool,
+        folder_id: String,
+        path: String,
+        md5_checksum: Option<String>,
+    ) -> GoogleDriveFileInfo {
         GoogleDriveFileInfo {
             id,
             size,
             name,
             shared,
+            folder_id,
+            path,
+            md5_checksum,
         }
     }
 }
@@ -72,6 +86,31 @@ impl TinfoilFileLike for GoogleDriveFileInfo {
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    fn get_checksum(&self) -> Option<&str> {
+        self.md5_checksum.as_deref()
+    }
+
+    fn get_folder_url(&self) -> String {
+        let folder_name = self
+            .path
+            .rsplit_once('/')
+            .and_then(|(parent, _)| parent.rsplit('/').next())
+            .unwrap_or("");
+
+        match folder_name.is_empty() {
+            true => format!("gdrive:{}", &self.folder_id),
+            false => format!(
+                "gdrive:{}#{}",
+                &self.folder_id,
+                utf8_percent_encode(folder_name, FRAGMENT)
+            ),
+        }
+    }
 }
 
 impl GoogleDriveFileInfo {
@@ -1,3 +1,4 @@
+use crate::fs::ignore::IgnoreRules;
 use crate::gdrive::gdrive_api::GoogleDriveApiService;
 use crate::gdrive::model::{GoogleDriveFileInfo, GoogleDriveScanResult};
 use log::debug;
@@ -16,7 +17,13 @@ impl GoogleDriveService {
         &self,
         folder_ids: Vec<String>,
         no_recursion: bool,
+        no_ignore_files: bool,
     ) -> anyhow::Result<GoogleDriveScanResult> {
+        let ignore = match no_ignore_files {
+            true => None,
+            false => Some(IgnoreRules::default()),
+        };
+
         // Trigger Authentication if needed
         self.api.trigger_auth().await?;
 
@@ -25,7 +32,7 @@ impl GoogleDriveService {
         for id in &folder_ids {
             let scan = self
                 .api
-                .get_all_files_in_folder(id.as_str(), !no_recursion)
+                .get_all_files_in_folder(id.as_str(), !no_recursion, "", ignore.as_ref())
                 .await?;
 
             debug!(
@@ -0,0 +1,390 @@
+use crate::tinfoil::user::{TinfoilUser, TinfoilUsers};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hyper::header::AUTHORIZATION;
+use hyper::HeaderMap;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Values Tinfoil sends with every request, credentials come from the basic auth of its location
+#[derive(Debug, Clone, Default)]
+pub struct TinfoilRequestInfo {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub uid: Option<String>,
+    pub hauth: Option<String>,
+    pub uauth: Option<String>,
+    pub theme: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthError {
+    MissingCredentials,
+    InvalidCredentials,
+    Disabled,
+    Expired,
+    DeviceNotAllowed,
+}
+
+/// The users file & one encoded error index per [AuthError], served instead of the real index
+#[derive(Debug, Clone)]
+pub struct TinfoilAuth {
+    pub users: TinfoilUsers,
+    pub error_indexes: HashMap<AuthError, Vec<u8>>,
+    /// Encoded indexes of users with index fields of their own, by username
+    pub user_indexes: HashMap<String, Vec<u8>>,
+}
+
+impl AuthError {
+    pub const ALL: [AuthError; 5] = [
+        AuthError::MissingCredentials,
+        AuthError::InvalidCredentials,
+        AuthError::Disabled,
+        AuthError::Expired,
+        AuthError::DeviceNotAllowed,
+    ];
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::MissingCredentials => {
+                write!(
+                    f,
+                    "No credentials provided, add a username & password to the location"
+                )
+            }
+            AuthError::InvalidCredentials => write!(f, "Invalid username or password"),
+            AuthError::Disabled => write!(f, "This account is disabled"),
+            AuthError::Expired => write!(f, "This account has expired"),
+            AuthError::DeviceNotAllowed => write!(f, "This device is not allowed for this account"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl TinfoilAuth {
+    pub fn authenticate(
+        &self,
+        request: &TinfoilRequestInfo,
+        now: DateTime<Utc>,
+    ) -> Result<&TinfoilUser, AuthError> {
+        let (username, password) = match (&request.username, &request.password) {
+            (Some(username), Some(password)) => (username, password),
+            _ => return Err(AuthError::MissingCredentials),
+        };
+
+        let user = match self.users.find(username) {
+            Some(user) if user.verify_password(password) => user,
+            Some(_) => return Err(AuthError::InvalidCredentials),
+            None => {
+                // verify anyway, unknown usernames would otherwise answer faster than wrong passwords
+                if let Some(user) = self.users.users.first() {
+                    user.verify_password(password);
+                }
+
+                return Err(AuthError::InvalidCredentials);
+            }
+        };
+
+        if !user.enabled {
+            return Err(AuthError::Disabled);
+        }
+
+        if user.expires.is_some_and(|expires| expires <= now) {
+            return Err(AuthError::Expired);
+        }
+
+        if !pin_matches(&user.uid, &request.uid) || !pin_matches(&user.hauth, &request.hauth) {
+            return Err(AuthError::DeviceNotAllowed);
+        }
+
+        Ok(user)
+    }
+}
+
+impl TinfoilRequestInfo {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+
+        let (username, password) = match headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(decode_basic_auth)
+        {
+            Some((username, password)) => (Some(username), Some(password)),
+            None => (None, None),
+        };
+
+        Self {
+            username,
+            password,
+            uid: header("UID"),
+            hauth: header("HAUTH"),
+            uauth: header("UAUTH"),
+            theme: header("Theme"),
+            version: header("Version"),
+        }
+    }
+}
+
+/// A pin that isn't set allows any value, header values are hex so they are compared case-insensitively
+fn pin_matches(pin: &Option<String>, value: &Option<String>) -> bool {
+    match (pin, value) {
+        (None, _) => true,
+        (Some(pin), Some(value)) => pin.eq_ignore_ascii_case(value),
+        (Some(_), None) => false,
+    }
+}
+
+fn decode_basic_auth(header: &str) -> Option<(String, String)> {
+    let (scheme, credentials) = header.trim().split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded = String::from_utf8(STANDARD.decode(credentials.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+
+    Some((username.to_string(), password.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use argon2::{Algorithm, Argon2, Params, Version};
+    use chrono::TimeZone;
+
+    fn auth(users: &str) -> TinfoilAuth {
+        TinfoilAuth {
+            users: TinfoilUsers::parse(users).unwrap(),
+            error_indexes: HashMap::new(),
+            user_indexes: HashMap::new(),
+        }
+    }
+
+    fn request(username: &str, password: &str) -> TinfoilRequestInfo {
+        TinfoilRequestInfo {
+            username: Some(username.to_string()),
+            password: Some(password.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    const USERS: &str = r#"
+        [[user]]
+        username = "alice"
+        password = "secret"
+
+        [[user]]
+        username = "bob"
+        password = "hunter2"
+        enabled = false
+
+        [[user]]
+        username = "carol"
+        password = "secret"
+        expires = 2024-06-01T12:00:00Z
+
+        [[user]]
+        username = "dave"
+        password = "secret"
+        uid = "0123456789ABCDEF"
+        hauth = "FEDCBA9876543210"
+    "#;
+
+    #[test]
+    fn authenticates_valid_credentials() {
+        let auth = auth(USERS);
+
+        let user = auth
+            .authenticate(&request("alice", "secret"), now())
+            .unwrap();
+        assert_eq!(user.username, "alice");
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_credentials() {
+        let auth = auth(USERS);
+
+        let missing = TinfoilRequestInfo::default();
+        assert_eq!(
+            auth.authenticate(&missing, now()).unwrap_err(),
+            AuthError::MissingCredentials
+        );
+
+        let mut username_only = request("alice", "");
+        username_only.password = None;
+        assert_eq!(
+            auth.authenticate(&username_only, now()).unwrap_err(),
+            AuthError::MissingCredentials
+        );
+
+        for (username, password) in [("alice", "wrong"), ("alice", "secret "), ("eve", "secret")] {
+            assert_eq!(
+                auth.authenticate(&request(username, password), now())
+                    .unwrap_err(),
+                AuthError::InvalidCredentials
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_disabled_and_expired_users() {
+        let auth = auth(USERS);
+
+        assert_eq!(
+            auth.authenticate(&request("bob", "hunter2"), now())
+                .unwrap_err(),
+            AuthError::Disabled
+        );
+
+        // a wrong password doesn't reveal that the account is disabled
+        assert_eq!(
+            auth.authenticate(&request("bob", "wrong"), now())
+                .unwrap_err(),
+            AuthError::InvalidCredentials
+        );
+
+        assert_eq!(
+            auth.authenticate(&request("carol", "secret"), now())
+                .unwrap_err(),
+            AuthError::Expired
+        );
+
+        let earlier = now() - chrono::Duration::seconds(1);
+        assert!(auth
+            .authenticate(&request("carol", "secret"), earlier)
+            .is_ok());
+    }
+
+    #[test]
+    fn checks_device_pins() {
+        let auth = auth(USERS);
+
+        let mut pinned = request("dave", "secret");
+        pinned.uid = Some("0123456789abcdef".to_string());
+        pinned.hauth = Some("FEDCBA9876543210".to_string());
+        assert!(auth.authenticate(&pinned, now()).is_ok());
+
+        let mut other_uid = pinned.clone();
+        other_uid.uid = Some("0000000000000000".to_string());
+        assert_eq!(
+            auth.authenticate(&other_uid, now()).unwrap_err(),
+            AuthError::DeviceNotAllowed
+        );
+
+        let mut other_hauth = pinned.clone();
+        other_hauth.hauth = Some("0000000000000000".to_string());
+        assert_eq!(
+            auth.authenticate(&other_hauth, now()).unwrap_err(),
+            AuthError::DeviceNotAllowed
+        );
+
+        let mut missing_uid = pinned.clone();
+        missing_uid.uid = None;
+        assert_eq!(
+            auth.authenticate(&missing_uid, now()).unwrap_err(),
+            AuthError::DeviceNotAllowed
+        );
+    }
+
+    #[test]
+    fn verifies_hashed_passwords() {
+        // cheap parameters keep the test fast, they are part of the hash
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(1024, 1, 1, None).unwrap(),
+        );
+        let salt = SaltString::encode_b64(b"rustfoil-salt").unwrap();
+        let argon2_hash = argon2.hash_password(b"secret", &salt).unwrap().to_string();
+        let bcrypt_hash = bcrypt::hash("secret", 4).unwrap();
+
+        let auth = auth(
+            format!(
+                "[[user]]\nusername = \"argon\"\npassword = \"{}\"\n\n[[user]]\nusername = \"bcrypt\"\npassword = \"{}\"\n",
+                argon2_hash, bcrypt_hash
+            )
+            .as_str(),
+        );
+
+        for username in ["argon", "bcrypt"] {
+            assert!(auth
+                .authenticate(&request(username, "secret"), now())
+                .is_ok());
+            assert_eq!(
+                auth.authenticate(&request(username, "wrong"), now())
+                    .unwrap_err(),
+                AuthError::InvalidCredentials
+            );
+        }
+
+        // the hash itself isn't a valid password
+        assert!(auth
+            .authenticate(&request("argon", argon2_hash.as_str()), now())
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_password_hashes() {
+        for password in ["$argon2id$v=19$m=x,t=1,p=1$c2FsdA$aGFzaA", "$2b$04$short"] {
+            let users = format!(
+                "[[user]]\nusername = \"alice\"\npassword = \"{}\"\n",
+                password
+            );
+
+            assert!(TinfoilUsers::parse(users.as_str()).is_err());
+        }
+    }
+
+    #[test]
+    fn decodes_basic_auth() {
+        // "alice:secret"
+        assert_eq!(
+            decode_basic_auth("Basic YWxpY2U6c2VjcmV0"),
+            Some(("alice".to_string(), "secret".to_string()))
+        );
+        assert_eq!(
+            decode_basic_auth(" basic  YWxpY2U6c2VjcmV0 "),
+            Some(("alice".to_string(), "secret".to_string()))
+        );
+
+        // "alice:se:cret", only the first colon separates the password
+        assert_eq!(
+            decode_basic_auth("Basic YWxpY2U6c2U6Y3JldA=="),
+            Some(("alice".to_string(), "se:cret".to_string()))
+        );
+
+        // ":"
+        assert_eq!(
+            decode_basic_auth("Basic Og=="),
+            Some((String::new(), String::new()))
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_basic_auth() {
+        assert_eq!(decode_basic_auth("Bearer YWxpY2U6c2VjcmV0"), None);
+        assert_eq!(decode_basic_auth("Basic"), None);
+        assert_eq!(decode_basic_auth("Basic not-base64!"), None);
+        // "alice", no colon
+        assert_eq!(decode_basic_auth("Basic YWxpY2U="), None);
+        // invalid utf-8
+        assert_eq!(decode_basic_auth("Basic /w=="), None);
+    }
+}
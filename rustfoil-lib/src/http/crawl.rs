@@ -0,0 +1,525 @@
+use crate::http::model::HttpFile;
+use lazy_static::lazy_static;
+use log::{debug, info};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use url::Url;
+
+/// Limits how deep & with how many concurrent requests a listing is crawled
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Number of subdirectory levels to descend, `Some(0)` only reads the top listing
+    pub max_depth: Option<usize>,
+    /// Maximum number of concurrent HEAD requests
+    pub workers: usize,
+}
+
+/// A listing or file the crawler could not read, the rest of the crawl continues
+#[derive(Debug, Clone)]
+pub struct CrawlWarning {
+    pub url: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CrawlResult {
+    pub files: Vec<HttpFile>,
+    pub warnings: Vec<CrawlWarning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ListingEntry {
+    Directory(Url),
+    File(Url, Option<u64>),
+}
+
+/// Entry of nginx `autoindex_format json`
+#[derive(Debug, Deserialize)]
+struct NginxJsonEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    size: Option<u64>,
+}
+
+impl CrawlWarning {
+    fn new(url: &Url, message: impl ToString) -> Self {
+        Self {
+            url: url.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for CrawlWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.url, self.message)
+    }
+}
+
+/// Crawls nginx (HTML & JSON), Apache & lighttpd directory listings below the url, only failing if the url itself can't be read
+pub async fn crawl_listing(url: &str, options: &CrawlOptions) -> anyhow::Result<CrawlResult> {
+    let root = match url.ends_with('/') {
+        true => Url::parse(url)?,
+        false => Url::parse(format!("{}/", url).as_str())?,
+    };
+
+    let client = Client::new();
+    let mut result = CrawlResult::default();
+    let mut visited = HashSet::from([root.clone()]);
+    let mut queue = VecDeque::from([(root.clone(), 0)]);
+    let mut without_size = Vec::new();
+
+    while let Some((directory, depth)) = queue.pop_front() {
+        let entries = match read_listing(&client, &directory).await {
+            Ok(entries) => entries,
+            Err(e) if directory == root => return Err(e),
+            Err(e) => {
+                result.warnings.push(CrawlWarning::new(&directory, e));
+                continue;
+            }
+        };
+
+        debug!("Read {} entries from {}", entries.len(), directory);
+
+        for entry in entries {
+            match entry {
+                ListingEntry::Directory(url) => {
+                    if options
+                        .max_depth
+                        .is_some_and(|max_depth| depth >= max_depth)
+                    {
+                        continue;
+                    }
+
+                    if visited.insert(url.clone()) {
+                        queue.push_back((url, depth + 1));
+                    }
+                }
+                ListingEntry::File(url, Some(size)) => {
+                    result.files.push(to_http_file(&root, url, size))
+                }
+                ListingEntry::File(url, None) => without_size.push(url),
+            }
+        }
+    }
+
+    if !without_size.is_empty() {
+        info!(
+            "Requesting the size of {} file(s) the listings don't state exactly",
+            without_size.len()
+        );
+    }
+
+    let semaphore = Arc::new(Semaphore::new(options.workers.max(1)));
+    let mut requests = JoinSet::new();
+
+    for url in without_size {
+        let client = client.clone();
+        let semaphore = semaphore.clone();
+
+        requests.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let size = read_content_length(&client, &url).await;
+
+            (url, size)
+        });
+    }
+
+    while let Some(joined) = requests.join_next().await {
+        match joined? {
+            (url, Ok(size)) => result.files.push(to_http_file(&root, url, size)),
+            (url, Err(e)) => result.warnings.push(CrawlWarning::new(&url, e)),
+        }
+    }
+
+    // HEAD requests finish in any order
+    result.files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(result)
+}
+
+async fn read_listing(client: &Client, directory: &Url) -> anyhow::Result<Vec<ListingEntry>> {
+    let response = client
+        .get(directory.clone())
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+
+    let body = response.text().await?;
+
+    match is_json {
+        true => parse_json_listing(directory, body.as_str()),
+        false => Ok(parse_html_listing(directory, body.as_str())),
+    }
+}
+
+async fn read_content_length(client: &Client, url: &Url) -> anyhow::Result<u64> {
+    let response = client.head(url.clone()).send().await?.error_for_status()?;
+
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .ok_or(anyhow::anyhow!("HEAD response has no Content-Length"))
+}
+
+fn parse_json_listing(directory: &Url, body: &str) -> anyhow::Result<Vec<ListingEntry>> {
+    let entries: Vec<NginxJsonEntry> = serde_json::from_str(body)?;
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| {
+            let name = urlencoding::encode(entry.name.as_str());
+
+            match entry.kind.as_str() {
+                "directory" => directory
+                    .join(format!("{}/", name).as_str())
+                    .ok()
+                    .map(ListingEntry::Directory),
+                "file" => directory
+                    .join(name.as_ref())
+                    .ok()
+                    .map(|url| ListingEntry::File(url, entry.size)),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Takes every link to a direct child of the directory, the exact size is only known for nginx listings
+fn parse_html_listing(directory: &Url, body: &str) -> Vec<ListingEntry> {
+    lazy_static! {
+        // Apache doesn't escape `'` inside double quoted hrefs, so the value ends at the quote it started with
+        static ref LINK_REGEX: Regex = Regex::new(
+            r#"(?is)<a\s[^>]*?href\s*=\s*(?:"([^"]*)"|'([^']*)')[^>]*>.*?</a>([^<\r\n]*)"#
+        )
+        .unwrap();
+        // nginx: `<a href="file">file</a>    18-Oct-2026 09:36    1228`
+        static ref NGINX_SIZE_REGEX: Regex =
+            Regex::new(r"^\s+\d{2}-\w{3}-\d{4} \d{2}:\d{2}\s+(\d+)\s*$").unwrap();
+    }
+
+    let mut seen = HashSet::new();
+
+    LINK_REGEX
+        .captures_iter(body)
+        .filter_map(|captures| {
+            let href = unescape_html(captures.get(1).or(captures.get(2))?.as_str());
+
+            // Apache sort links & anchors
+            if href.is_empty() || href.starts_with('?') || href.starts_with('#') {
+                return None;
+            }
+
+            let mut url = directory.join(href.as_str()).ok()?;
+            url.set_fragment(None);
+
+            if url.query().is_some() || !seen.insert(url.clone()) {
+                return None;
+            }
+
+            let relative = url.as_str().strip_prefix(directory.as_str())?;
+
+            match relative.strip_suffix('/') {
+                Some(name) if !name.is_empty() && !name.contains('/') => {
+                    Some(ListingEntry::Directory(url))
+                }
+                None if !relative.is_empty() && !relative.contains('/') => {
+                    let size = captures
+                        .get(3)
+                        .and_then(|rest| NGINX_SIZE_REGEX.captures(rest.as_str()))
+                        .and_then(|size| size[1].parse::<u64>().ok());
+
+                    Some(ListingEntry::File(url, size))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn to_http_file(root: &Url, url: Url, size: u64) -> HttpFile {
+    let path = url
+        .as_str()
+        .strip_prefix(root.as_str())
+        .unwrap_or(url.path())
+        .split('/')
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().to_string())
+        .collect::<Vec<String>>();
+
+    let name = path.last().cloned().unwrap_or_default();
+
+    HttpFile::new(url.to_string(), size as i64, name, path.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    const NGINX_HTML: &str = r#"<html>
+<head><title>Index of /switch/</title></head>
+<body>
+<h1>Index of /switch/</h1><hr><pre><a href="../">../</a>
+<a href="Apache/">Apache/</a>                                            18-Oct-2026 09:36                   -
+<a href="Game%20%5B0100000000AA0000%5D.nsp">Game [0100000000AA0000].nsp</a>        18-Oct-2026 09:36                1228
+<a href="Tom%20%26%20Jerry.nsp">Tom &amp; Jerry.nsp</a>                         18-Oct-2026 09:36                5000
+</pre><hr></body>
+</html>
+"#;
+
+    const APACHE_HTML: &str = r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 3.2 Final//EN">
+<html>
+ <head>
+  <title>Index of /switch/Apache</title>
+ </head>
+ <body>
+<h1>Index of /switch/Apache</h1>
+  <table>
+   <tr><th valign="top"><img src="/icons/blank.gif" alt="[ICO]"></th><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=M;O=A">Last modified</a></th><th><a href="?C=S;O=A">Size</a></th></tr>
+<tr><td valign="top"><img src="/icons/back.gif" alt="[PARENTDIR]"></td><td><a href="/switch/">Parent Directory</a></td><td>&nbsp;</td><td align="right">  - </td></tr>
+<tr><td valign="top"><img src="/icons/unknown.gif" alt="[   ]"></td><td><a href="Assassin's%20Creed%20%5B0100000000AB0000%5D.nsp">Assassin's Creed [0100000000AB0000].nsp</a></td><td align="right">2026-10-18 09:36  </td><td align="right">4.9K</td></tr>
+<tr><td valign="top"><img src="/icons/folder.gif" alt="[DIR]"></td><td><a href="Lighttpd/">Lighttpd/</a></td><td align="right">2026-10-18 09:36  </td><td align="right">  - </td></tr>
+</table>
+</body></html>
+"#;
+
+    const LIGHTTPD_HTML: &str = r#"<!DOCTYPE html>
+<html><head><title>Index of /switch/Apache/Lighttpd/</title></head><body><h2>Index of /switch/Apache/Lighttpd/</h2><div class="list"><table summary="Directory Listing" cellpadding="0" cellspacing="0"><thead><tr><th class="n">Name</th><th class="m">Last Modified</th><th class="s">Size</th><th class="t">Type</th></tr></thead><tbody>
+<tr class="d"><td class="n"><a href="../">..</a>/</td><td class="m">&nbsp;</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr><td class="n"><a href="Update%20%5B0100000000AA0800%5D%5Bv65536%5D.nsp">Update [0100000000AA0800][v65536].nsp</a></td><td class="m">2026-Oct-18 09:36:00</td><td class="s">1.2K</td><td class="t">application/octet-stream</td></tr>
+</tbody></table></div></body></html>
+"#;
+
+    const NGINX_JSON: &str = r#"[
+{ "name":"Game [0100000000AA0000].nsp", "type":"file", "mtime":"Sat, 18 Oct 2026 09:36:00 GMT", "size":1228 },
+{ "name":"Sub Dir", "type":"directory", "mtime":"Sat, 18 Oct 2026 09:36:00 GMT" },
+{ "name":"link", "type":"other", "mtime":"Sat, 18 Oct 2026 09:36:00 GMT" }
+]"#;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn parses_nginx_html_with_exact_sizes() {
+        let entries = parse_html_listing(&url("http://host/switch/"), NGINX_HTML);
+
+        assert_eq!(
+            entries,
+            vec![
+                ListingEntry::Directory(url("http://host/switch/Apache/")),
+                ListingEntry::File(
+                    url("http://host/switch/Game%20%5B0100000000AA0000%5D.nsp"),
+                    Some(1228)
+                ),
+                ListingEntry::File(url("http://host/switch/Tom%20%26%20Jerry.nsp"), Some(5000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_apache_html_without_sort_or_parent_links() {
+        let entries = parse_html_listing(&url("http://host/switch/Apache/"), APACHE_HTML);
+
+        assert_eq!(
+            entries,
+            vec![
+                ListingEntry::File(
+                    url(
+                        "http://host/switch/Apache/Assassin's%20Creed%20%5B0100000000AB0000%5D.nsp"
+                    ),
+                    None
+                ),
+                ListingEntry::Directory(url("http://host/switch/Apache/Lighttpd/")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_lighttpd_html_without_rounded_sizes() {
+        let entries = parse_html_listing(&url("http://host/l/"), LIGHTTPD_HTML);
+
+        assert_eq!(
+            entries,
+            vec![ListingEntry::File(
+                url("http://host/l/Update%20%5B0100000000AA0800%5D%5Bv65536%5D.nsp"),
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn keeps_single_quoted_hrefs_containing_double_quotes() {
+        let entries = parse_html_listing(
+            &url("http://host/"),
+            r#"<a href='Say "Hi".nsp'>Say "Hi".nsp</a>"#,
+        );
+
+        assert_eq!(
+            entries,
+            vec![ListingEntry::File(
+                url("http://host/Say%20%22Hi%22.nsp"),
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn ignores_links_outside_the_directory() {
+        let body = r##"<a href="http://other/file.nsp">x</a><a href="/file.nsp">x</a>
+<a href="Sub/Deep/file.nsp">x</a><a href="#top">x</a><a href="file.nsp?download">x</a>"##;
+
+        assert!(parse_html_listing(&url("http://host/switch/"), body).is_empty());
+    }
+
+    #[test]
+    fn parses_nginx_json() {
+        let entries = parse_json_listing(&url("http://host/switch/"), NGINX_JSON).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                ListingEntry::File(
+                    url("http://host/switch/Game%20%5B0100000000AA0000%5D.nsp"),
+                    Some(1228)
+                ),
+                ListingEntry::Directory(url("http://host/switch/Sub%20Dir/")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        assert!(parse_json_listing(&url("http://host/"), "<html>").is_err());
+    }
+
+    /// Serves the fixtures as a static server would below `/switch/`, file sizes are only available via HEAD
+    async fn serve_fixtures() -> SocketAddr {
+        let service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|request: Request<Body>| async move {
+                let (content_type, body) = match request.uri().path() {
+                    "/switch/" => ("text/html", NGINX_HTML.to_string()),
+                    "/switch/Apache/" => ("text/html", APACHE_HTML.to_string()),
+                    "/switch/Apache/Lighttpd/" => ("text/html", LIGHTTPD_HTML.to_string()),
+                    "/switch/Apache/Assassin's%20Creed%20%5B0100000000AB0000%5D.nsp" => {
+                        ("application/octet-stream", "x".repeat(4990))
+                    }
+                    _ => {
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::NOT_FOUND;
+
+                        return Ok::<_, Infallible>(response);
+                    }
+                };
+
+                let mut response = Response::new(Body::from(body.clone()));
+                let headers = response.headers_mut();
+
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+                headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+
+                Ok(response)
+            }))
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service);
+        let address = server.local_addr();
+
+        tokio::spawn(server);
+
+        address
+    }
+
+    #[tokio::test]
+    async fn crawls_a_local_server() {
+        let address = serve_fixtures().await;
+        let options = CrawlOptions {
+            max_depth: None,
+            workers: 2,
+        };
+
+        let result = crawl_listing(&format!("http://{}/switch", address), &options)
+            .await
+            .unwrap();
+
+        let files = result
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.size))
+            .collect::<Vec<(&str, i64)>>();
+
+        assert_eq!(
+            files,
+            vec![
+                ("Apache/Assassin's Creed [0100000000AB0000].nsp", 4990),
+                ("Game [0100000000AA0000].nsp", 1228),
+                ("Tom & Jerry.nsp", 5000),
+            ]
+        );
+
+        // the lighttpd file isn't served, its HEAD request fails
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].url.contains("Update"));
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_depth() {
+        let address = serve_fixtures().await;
+        let options = CrawlOptions {
+            max_depth: Some(0),
+            workers: 1,
+        };
+
+        let result = crawl_listing(&format!("http://{}/switch/", address), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(result.files.len(), 2);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_if_the_root_listing_is_missing() {
+        let address = serve_fixtures().await;
+        let options = CrawlOptions {
+            max_depth: None,
+            workers: 1,
+        };
+
+        assert!(
+            crawl_listing(&format!("http://{}/missing/", address), &options)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1 +1,5 @@
+pub mod auth;
+pub mod crawl;
 pub mod model;
+pub mod server;
+pub mod tls;
@@ -1,5 +1,8 @@
 use crate::abstraction::file::TinfoilFileLike;
 use crate::fs::file::LocalFile;
+use crate::title::model::TitleInfo;
+use crate::title::parser::parse_title_info;
+use std::ffi::OsStr;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -7,6 +10,11 @@ pub struct HttpFile {
     pub url: String,
     pub size: i64,
     pub name: String,
+    pub path: String,
+    /// Title info read from the file content, used when the name has none
+    pub title_info: Option<TitleInfo>,
+    /// Path of the file on disk, if it was scanned locally
+    pub local_path: Option<PathBuf>,
 }
 
 impl TinfoilFileLike for HttpFile {
@@ -21,11 +29,51 @@ impl TinfoilFileLike for HttpFile {
     fn get_name(&self) -> &str {
         self.name.as_str()
     }
+
+    fn get_path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    fn get_title_info(&self) -> Option<TitleInfo> {
+        parse_title_info(self.name.as_str()).or_else(|| self.title_info.clone())
+    }
+
+    fn get_folder_url(&self) -> String {
+        match self.url.rsplit_once('/') {
+            None => self.url.clone(),
+            Some((folder, _)) => format!("{}/", folder),
+        }
+    }
 }
 
 impl HttpFile {
-    pub fn new(url: String, size: i64, name: String) -> HttpFile {
-        HttpFile { url, size, name }
+    pub fn new(url: String, size: i64, name: String, path: String) -> HttpFile {
+        HttpFile {
+            url,
+            size,
+            name,
+            path,
+            title_info: None,
+            local_path: None,
+        }
+    }
+
+    /// Attaches title info read from the content & appends a name carrying it to the url, as Tinfoil takes the name from the fragment
+    pub fn with_title_info(mut self, info: TitleInfo) -> HttpFile {
+        let (stem, extension) = self
+            .name
+            .rsplit_once('.')
+            .unwrap_or((self.name.as_str(), "nsp"));
+
+        let name = match info.version {
+            None => format!("{} [{}].{}", stem, info.title_id, extension),
+            Some(version) => format!("{} [{}][v{}].{}", stem, info.title_id, version, extension),
+        };
+
+        self.url = format!("{}#{}", self.url, urlencoding::encode(name.as_str()));
+        self.title_info = Some(info);
+
+        self
     }
 
     pub fn from_local_with_base_url(
@@ -35,15 +83,50 @@ impl HttpFile {
     ) -> anyhow::Result<HttpFile> {
         let path = local_file.path.strip_prefix(base_path)?;
 
-        let file_name = path.file_name().unwrap().to_str().unwrap();
-        let parent = path.parent().unwrap().to_str().unwrap();
+        let mut url_segments = Vec::new();
+        let mut folders = Vec::new();
+
+        for component in path.components() {
+            let segment = component.as_os_str();
+
+            url_segments.push(encode_segment(segment));
+            folders.push(segment.to_string_lossy().to_string());
+        }
 
-        let file_path = format!("{}/{}", parent, urlencoding::encode(file_name));
+        // the last component is the file itself, whose name was already decoded by the walker
+        if folders.pop().is_none() {
+            return Err(anyhow::anyhow!(
+                "{:?} is not a file inside {:?}",
+                local_file.path,
+                base_path
+            ));
+        }
+
+        folders.push(local_file.name.clone());
 
         Ok(HttpFile {
-            url: base_url.to_string() + file_path.as_str(),
+            url: format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                url_segments.join("/")
+            ),
             size: local_file.size as i64,
             name: local_file.name.clone(),
+            path: folders.join("/"),
+            title_info: None,
+            local_path: Some(local_file.path.clone()),
         })
     }
 }
+
+#[cfg(unix)]
+fn encode_segment(segment: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    urlencoding::encode_binary(segment.as_bytes()).to_string()
+}
+
+#[cfg(not(unix))]
+fn encode_segment(segment: &OsStr) -> String {
+    urlencoding::encode(&segment.to_string_lossy()).to_string()
+}
@@ -0,0 +1,533 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::http::auth::{TinfoilAuth, TinfoilRequestInfo};
+use crate::http::model::HttpFile;
+use crate::http::tls::TinfoilTls;
+use crate::title::model::TitleInfo;
+use chrono::Utc;
+use hyper::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use hyper::server::conn::{AddrStream, Http};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{debug, info, warn};
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::io::ReaderStream;
+
+#[derive(Debug, Clone)]
+struct ServedFile {
+    path: PathBuf,
+    size: u64,
+    title_info: Option<TitleInfo>,
+}
+
+/// Serves an in memory index at `/` & the indexed local files below the path of the base url
+pub struct TinfoilServer {
+    index: Vec<u8>,
+    /// Url path of the base url, e.g. `/` or `/games/`
+    base_path: String,
+    /// Percent decoded url path relative to the base path
+    files: HashMap<Vec<u8>, ServedFile>,
+    auth: Option<TinfoilAuth>,
+    tls: Option<TinfoilTls>,
+}
+
+/// Result of matching a `Range` header against the size of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    Partial(RangeInclusive<u64>),
+    Unsatisfiable,
+}
+
+impl TinfoilServer {
+    /// Only files with a local path are served, requests for anything else are answered with 404
+    pub fn new(base_url: &str, index: Vec<u8>, files: &[HttpFile]) -> anyhow::Result<Self> {
+        let base_path = url::Url::parse(base_url)?.path().to_string();
+        let base_url = base_url.trim_end_matches('/');
+
+        let mut served = HashMap::new();
+
+        for file in files {
+            let local_path = match &file.local_path {
+                None => continue,
+                Some(path) => path,
+            };
+
+            let url = file.url.split('#').next().unwrap_or(file.url.as_str());
+
+            let relative = match url.strip_prefix(base_url) {
+                None => continue,
+                Some(relative) => relative.trim_start_matches('/'),
+            };
+
+            served
+                .entry(percent_decode_str(relative).collect())
+                .or_insert(ServedFile {
+                    path: local_path.clone(),
+                    size: file.size as u64,
+                    title_info: file.get_title_info(),
+                });
+        }
+
+        Ok(Self {
+            index,
+            base_path: match base_path.ends_with('/') {
+                true => base_path,
+                false => format!("{}/", base_path),
+            },
+            files: served,
+            auth: None,
+            tls: None,
+        })
+    }
+
+    /// Requires every request to authenticate against the users, failed index requests get the matching error index
+    pub fn with_auth(mut self, auth: TinfoilAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Serves over TLS, with client authentication files are only served to clients with a valid certificate
+    pub fn with_tls(mut self, tls: TinfoilTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub async fn serve(self, bind: SocketAddr) -> anyhow::Result<()> {
+        if let Some(tls) = &self.tls {
+            let acceptor = TlsAcceptor::from(tls.config.clone());
+
+            return Arc::new(self).serve_tls(bind, acceptor).await;
+        }
+
+        let server = Arc::new(self);
+
+        let make_service = make_service_fn(move |connection: &AddrStream| {
+            let server = server.clone();
+            let remote = connection.remote_addr();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    server.clone().handle(request, remote, false)
+                }))
+            }
+        });
+
+        let server = Server::try_bind(&bind)?.serve(make_service);
+
+        info!("Serving Index at http://{}/", server.local_addr());
+
+        server.await?;
+
+        Ok(())
+    }
+
+    async fn serve_tls(
+        self: Arc<Self>,
+        bind: SocketAddr,
+        acceptor: TlsAcceptor,
+    ) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(bind).await?;
+
+        info!("Serving Index at https://{}/", listener.local_addr()?);
+
+        loop {
+            let (stream, remote) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+
+            let server = self.clone();
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        debug!("TLS handshake with {} failed: {}", remote, e);
+                        return;
+                    }
+                };
+
+                // rustls only accepts the connection if a presented certificate is signed by the client CA
+                let client_certificate = stream.get_ref().1.peer_certificates().is_some();
+
+                let service = service_fn(move |request| {
+                    server.clone().handle(request, remote, client_certificate)
+                });
+
+                if let Err(e) = Http::new().serve_connection(stream, service).await {
+                    debug!("Connection to {} failed: {}", remote, e);
+                }
+            });
+        }
+    }
+
+    async fn handle(
+        self: Arc<Self>,
+        request: Request<Body>,
+        remote: SocketAddr,
+        client_certificate: bool,
+    ) -> Result<Response<Body>, Infallible> {
+        debug!("{} {} from {}", request.method(), request.uri(), remote);
+
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return Ok(status(StatusCode::METHOD_NOT_ALLOWED));
+        }
+
+        let path = request.uri().path();
+
+        let mut index = &self.index;
+        let mut allowlist = None;
+
+        if let Some(auth) = &self.auth {
+            let info = TinfoilRequestInfo::from_headers(request.headers());
+
+            match auth.authenticate(&info, Utc::now()) {
+                Ok(user) => {
+                    debug!(
+                        "Authenticated {} from {} (Tinfoil {}, Theme {})",
+                        user.username,
+                        remote,
+                        info.version.as_deref().unwrap_or("unknown"),
+                        info.theme.as_deref().unwrap_or("unknown")
+                    );
+
+                    if let Some(user_index) = auth.user_indexes.get(&user.username) {
+                        index = user_index;
+                    }
+
+                    allowlist = user.title_allowlist.as_ref();
+                }
+                Err(e) => {
+                    warn!(
+                        "Rejected {} from {} as {}: {}",
+                        path,
+                        remote,
+                        info.username.as_deref().unwrap_or("anonymous"),
+                        e
+                    );
+
+                    // Tinfoil only shows the message of an index, files get a plain 401
+                    return Ok(match (path, auth.error_indexes.get(&e)) {
+                        ("/", Some(index)) => index_response(&request, index),
+                        _ => status(StatusCode::UNAUTHORIZED),
+                    });
+                }
+            }
+        }
+
+        if path == "/" {
+            return Ok(index_response(&request, index));
+        }
+
+        if self.tls.as_ref().is_some_and(|tls| tls.client_auth) && !client_certificate {
+            warn!("Rejected {} from {}: no client certificate", path, remote);
+            return Ok(status(StatusCode::FORBIDDEN));
+        }
+
+        let file = path
+            .strip_prefix(self.base_path.as_str())
+            .map(|relative| percent_decode_str(relative).collect::<Vec<u8>>())
+            .and_then(|relative| self.files.get(&relative));
+
+        let file = match file {
+            None => return Ok(status(StatusCode::NOT_FOUND)),
+            Some(file) => file,
+        };
+
+        // users with an allowlist may only download the files of their own index
+        if let Some(allowlist) = allowlist {
+            if !file
+                .title_info
+                .as_ref()
+                .is_some_and(|info| allowlist.contains(info))
+            {
+                warn!(
+                    "Rejected {} from {}: title id not on allowlist",
+                    path, remote
+                );
+                return Ok(status(StatusCode::FORBIDDEN));
+            }
+        }
+
+        match serve_file(&request, file).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                warn!("Failed to serve {:?}: {}", file.path, e);
+                Ok(status(StatusCode::INTERNAL_SERVER_ERROR))
+            }
+        }
+    }
+}
+
+fn index_response(request: &Request<Body>, index: &[u8]) -> Response<Body> {
+    let body = match request.method() == Method::HEAD {
+        true => Body::empty(),
+        false => Body::from(index.to_vec()),
+    };
+
+    Response::builder()
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .header(CONTENT_LENGTH, index.len())
+        .body(body)
+        .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+async fn serve_file(request: &Request<Body>, file: &ServedFile) -> anyhow::Result<Response<Body>> {
+    // the size might have changed since the scan
+    let size = tokio::fs::metadata(&file.path).await?.len();
+
+    if size != file.size {
+        debug!("{:?} changed size since the scan", file.path);
+    }
+
+    let range = match request.headers().get(RANGE) {
+        None => ByteRange::Full,
+        Some(header) => parse_range(header.to_str().unwrap_or(""), size),
+    };
+
+    let (response, range) = match range {
+        ByteRange::Unsatisfiable => {
+            return Ok(Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::empty())?);
+        }
+        ByteRange::Full => (Response::builder().status(StatusCode::OK), None),
+        ByteRange::Partial(range) => (
+            Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", range.start(), range.end(), size),
+                ),
+            Some(range),
+        ),
+    };
+
+    let (start, length) = match &range {
+        None => (0, size),
+        Some(range) => (*range.start(), range.end() - range.start() + 1),
+    };
+
+    let response = response
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .header(CONTENT_LENGTH, length)
+        .header(ACCEPT_RANGES, "bytes");
+
+    if request.method() == Method::HEAD {
+        return Ok(response.body(Body::empty())?);
+    }
+
+    let mut handle = File::open(&file.path).await?;
+    handle.seek(SeekFrom::Start(start)).await?;
+
+    Ok(response.body(Body::wrap_stream(ReaderStream::new(handle.take(length))))?)
+}
+
+/// Parses a single `bytes=` range, invalid & multiple ranges are ignored like RFC 9110 allows, which answers them with the full file
+fn parse_range(header: &str, size: u64) -> ByteRange {
+    let spec = match header.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        None => return ByteRange::Full,
+        Some(parts) => parts,
+    };
+
+    let (first, last) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // bytes=-N is the last N bytes, an empty file has no range to send
+        (Err(_), Ok(suffix)) if start.is_empty() => {
+            return match (suffix, size) {
+                (0, _) => ByteRange::Unsatisfiable,
+                (_, 0) => ByteRange::Full,
+                _ => ByteRange::Partial(size.saturating_sub(suffix)..=size - 1),
+            };
+        }
+        (Ok(first), Err(_)) if end.is_empty() => (first, u64::MAX),
+        (Ok(first), Ok(last)) if first <= last => (first, last),
+        _ => return ByteRange::Full,
+    };
+
+    match first < size {
+        true => ByteRange::Partial(first..=last.min(size - 1)),
+        false => ByteRange::Unsatisfiable,
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderMap;
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0..=99));
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Partial(900..=999)
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial(900..=999)
+        );
+        assert_eq!(
+            parse_range(" bytes= 10-19 ", 1000),
+            ByteRange::Partial(10..=19)
+        );
+    }
+
+    #[test]
+    fn clamps_ranges_to_the_file() {
+        assert_eq!(
+            parse_range("bytes=990-2000", 1000),
+            ByteRange::Partial(990..=999)
+        );
+        assert_eq!(
+            parse_range("bytes=-5000", 1000),
+            ByteRange::Partial(0..=999)
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_and_multiple_ranges() {
+        assert_eq!(parse_range("bytes=5-3", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=abc", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=-", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(
+            parse_range("bytes=2000-3000", 1000),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn handles_empty_files() {
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-0", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-10", 0), ByteRange::Full);
+    }
+
+    async fn request(url: &str, method: reqwest::Method, range: Option<&str>) -> reqwest::Response {
+        let mut headers = HeaderMap::new();
+
+        if let Some(range) = range {
+            headers.insert(RANGE, range.parse().unwrap());
+        }
+
+        reqwest::Client::new()
+            .request(method, url)
+            .headers(headers)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_the_index_and_ranges_over_localhost() {
+        let folder = std::env::temp_dir().join(format!("rustfoil-server-{}", std::process::id()));
+        let path = folder.join("Game [0100000000AA0000].nsp");
+        let content = (0..=255u8).cycle().take(1000).collect::<Vec<u8>>();
+
+        tokio::fs::create_dir_all(&folder).await.unwrap();
+        tokio::fs::write(&path, &content).await.unwrap();
+
+        // the server doesn't report the port it got, so reserve one up front
+        let bind = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let base_url = format!("http://{}/games/", bind);
+
+        let mut file = HttpFile::new(
+            format!("{}Game%20%5B0100000000AA0000%5D.nsp", base_url),
+            content.len() as i64,
+            "Game [0100000000AA0000].nsp".to_string(),
+            "Game [0100000000AA0000].nsp".to_string(),
+        );
+        file.local_path = Some(path);
+
+        let server = TinfoilServer::new(&base_url, b"index".to_vec(), &[file.clone()]).unwrap();
+        tokio::spawn(server.serve(bind));
+
+        let index_url = format!("http://{}/", bind);
+
+        for _ in 0..50 {
+            if reqwest::get(&index_url).await.is_ok() {
+                break;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        let index = request(&index_url, reqwest::Method::GET, None).await;
+        assert_eq!(index.status(), 200);
+        assert_eq!(index.bytes().await.unwrap().as_ref(), b"index");
+
+        let full = request(&file.url, reqwest::Method::GET, None).await;
+        assert_eq!(full.status(), 200);
+        assert_eq!(full.headers()[ACCEPT_RANGES], "bytes");
+        assert_eq!(full.bytes().await.unwrap().as_ref(), content.as_slice());
+
+        let head = request(&file.url, reqwest::Method::HEAD, None).await;
+        assert_eq!(head.status(), 200);
+        assert_eq!(head.headers()[CONTENT_LENGTH], "1000");
+        assert!(head.bytes().await.unwrap().is_empty());
+
+        let partial = request(&file.url, reqwest::Method::GET, Some("bytes=100-199")).await;
+        assert_eq!(partial.status(), 206);
+        assert_eq!(partial.headers()[CONTENT_RANGE], "bytes 100-199/1000");
+        assert_eq!(partial.bytes().await.unwrap().as_ref(), &content[100..200]);
+
+        let suffix = request(&file.url, reqwest::Method::GET, Some("bytes=-10")).await;
+        assert_eq!(suffix.status(), 206);
+        assert_eq!(suffix.bytes().await.unwrap().as_ref(), &content[990..]);
+
+        let inverted = request(&file.url, reqwest::Method::GET, Some("bytes=5-3")).await;
+        assert_eq!(inverted.status(), 200);
+        assert_eq!(inverted.bytes().await.unwrap().len(), 1000);
+
+        let beyond = request(&file.url, reqwest::Method::GET, Some("bytes=1000-")).await;
+        assert_eq!(beyond.status(), 416);
+        assert_eq!(beyond.headers()[CONTENT_RANGE], "bytes */1000");
+
+        let missing = format!("{}Other.nsp", base_url);
+        assert_eq!(
+            request(&missing, reqwest::Method::GET, None).await.status(),
+            404
+        );
+
+        let post = request(&file.url, reqwest::Method::POST, None).await;
+        assert_eq!(post.status(), 405);
+
+        tokio::fs::remove_dir_all(&folder).await.unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs;
+use tokio_rustls::rustls::server::AllowAnyAnonymousOrAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+
+/// TLS settings of the built-in server
+#[derive(Clone)]
+pub struct TinfoilTls {
+    pub config: Arc<ServerConfig>,
+    /// Files are only served over connections which presented a certificate signed by the client CA
+    pub client_auth: bool,
+}
+
+impl TinfoilTls {
+    /// Reads the PEM encoded server certificate chain & key, a client CA enables client authentication
+    pub async fn read(
+        cert_path: &Path,
+        key_path: &Path,
+        client_ca_path: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let certs = read_certificates(cert_path).await?;
+        let key = read_private_key(key_path).await?;
+
+        let builder = ServerConfig::builder().with_safe_defaults();
+
+        let mut config = match client_ca_path {
+            None => builder.with_no_client_auth().with_single_cert(certs, key)?,
+            Some(client_ca_path) => {
+                let mut roots = RootCertStore::empty();
+
+                for certificate in read_certificates(client_ca_path).await? {
+                    roots.add(&certificate)?;
+                }
+
+                // the index has to be readable without a certificate, files are checked per request
+                builder
+                    .with_client_cert_verifier(
+                        AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+                    )
+                    .with_single_cert(certs, key)?
+            }
+        };
+
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(Self {
+            config: Arc::new(config),
+            client_auth: client_ca_path.is_some(),
+        })
+    }
+}
+
+async fn read_certificates(path: &Path) -> anyhow::Result<Vec<Certificate>> {
+    let pem = fs::read(path).await?;
+
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(pem.as_slice()))?;
+
+    if certificates.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{:?} does not contain a PEM encoded certificate",
+            path
+        ));
+    }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+async fn read_private_key(path: &Path) -> anyhow::Result<PrivateKey> {
+    let pem = fs::read(path).await?;
+    let mut reader = BufReader::new(pem.as_slice());
+
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => continue,
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "{:?} does not contain a PEM encoded private key",
+        path
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::model::HttpFile;
+    use crate::http::server::TinfoilServer;
+    use crate::tinfoil::certificate::{issue_client_certificate, TinfoilServerCertificates};
+
+    fn client(ca_cert: &str, identity: Option<String>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .add_root_certificate(reqwest::Certificate::from_pem(ca_cert.as_bytes()).unwrap());
+
+        if let Some(identity) = identity {
+            builder = builder.identity(reqwest::Identity::from_pem(identity.as_bytes()).unwrap());
+        }
+
+        builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_serves_files_to_clients_with_a_certificate() {
+        let folder = std::env::temp_dir().join(format!("rustfoil-tls-{}", std::process::id()));
+        let path = folder.join("Game.nsp");
+
+        let certificates =
+            TinfoilServerCertificates::generate(vec!["localhost".to_string()]).unwrap();
+        certificates.write(&folder, true).await.unwrap();
+        tokio::fs::write(&path, b"game").await.unwrap();
+
+        let client_certificate =
+            issue_client_certificate(&folder.join("ca.pem"), &folder.join("ca.key"))
+                .await
+                .unwrap();
+
+        let tls = TinfoilTls::read(
+            &folder.join("server.pem"),
+            &folder.join("server.key"),
+            Some(&folder.join("ca.pem")),
+        )
+        .await
+        .unwrap();
+        assert!(tls.client_auth);
+
+        // the server doesn't report the port it got, so reserve one up front
+        let bind = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let base_url = format!("https://localhost:{}/", bind.port());
+
+        let mut file = HttpFile::new(
+            format!("{}Game.nsp", base_url),
+            4,
+            "Game.nsp".to_string(),
+            "Game.nsp".to_string(),
+        );
+        file.local_path = Some(path);
+
+        let server = TinfoilServer::new(&base_url, b"index".to_vec(), &[file.clone()])
+            .unwrap()
+            .with_tls(tls);
+        tokio::spawn(server.serve(bind));
+
+        let anonymous = client(&certificates.ca_cert, None);
+
+        for _ in 0..50 {
+            if anonymous.get(&base_url).send().await.is_ok() {
+                break;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // the handshake succeeds without a certificate, but only the index is served
+        let index = anonymous.get(&base_url).send().await.unwrap();
+        assert_eq!(index.status(), 200);
+        assert_eq!(index.bytes().await.unwrap().as_ref(), b"index");
+
+        let denied = anonymous.get(&file.url).send().await.unwrap();
+        assert_eq!(denied.status(), 403);
+
+        let identity = format!(
+            "{}\n{}\n",
+            client_certificate.public, client_certificate.key
+        );
+        let authenticated = client(&certificates.ca_cert, Some(identity));
+
+        let allowed = authenticated.get(&file.url).send().await.unwrap();
+        assert_eq!(allowed.status(), 200);
+        assert_eq!(allowed.bytes().await.unwrap().as_ref(), b"game");
+
+        // a certificate from another CA fails the handshake
+        let other = TinfoilServerCertificates::generate(vec!["localhost".to_string()]).unwrap();
+        let identity = format!("{}\n{}\n", other.server_cert, other.server_key);
+        assert!(client(&certificates.ca_cert, Some(identity))
+            .get(&file.url)
+            .send()
+            .await
+            .is_err());
+
+        tokio::fs::remove_dir_all(&folder).await.unwrap();
+    }
+}
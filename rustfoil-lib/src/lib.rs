@@ -4,3 +4,4 @@ pub mod fs;
 pub mod gdrive;
 pub mod http;
 pub mod tinfoil;
+pub mod title;
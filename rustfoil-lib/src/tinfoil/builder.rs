@@ -0,0 +1,449 @@
+use crate::abstraction::file::TinfoilFileLike;
+use crate::tinfoil::certificate::TinfoilClientCertificate;
+use crate::tinfoil::model::{TinfoilFile, TinfoilIndex, TinfoilLocation};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TinfoilIndexBuilderError {
+    EmptyFileUrl { position: usize },
+    NegativeFileSize { url: String, size: i64 },
+    EmptyDirectoryUrl { position: usize },
+    InvalidHeader { header: String },
+    InvalidVersion { version: f32 },
+    InvalidLocation { url: String, reason: String },
+    IncompleteClientCertificate,
+}
+
+impl Display for TinfoilIndexBuilderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TinfoilIndexBuilderError::EmptyFileUrl { position } => {
+                write!(f, "File at position {} has an empty url", position)
+            }
+            TinfoilIndexBuilderError::NegativeFileSize { url, size } => {
+                write!(f, "File {} has a negative size of {}", url, size)
+            }
+            TinfoilIndexBuilderError::EmptyDirectoryUrl { position } => {
+                write!(f, "Directory at position {} has an empty url", position)
+            }
+            TinfoilIndexBuilderError::InvalidHeader { header } => {
+                write!(
+                    f,
+                    "Header \"{}\" is not in the format \"Name: value\"",
+                    header
+                )
+            }
+            TinfoilIndexBuilderError::InvalidVersion { version } => {
+                write!(f, "Version {} is not a positive number", version)
+            }
+            TinfoilIndexBuilderError::InvalidLocation { url, reason } => {
+                write!(f, "Location {} is invalid: {}", url, reason)
+            }
+            TinfoilIndexBuilderError::IncompleteClientCertificate => {
+                write!(
+                    f,
+                    "Client certificate requires both a public certificate and a private key"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TinfoilIndexBuilderError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct TinfoilIndexBuilder {
+    files: Option<Vec<TinfoilFile>>,
+    directories: Option<Vec<String>>,
+    success: Option<String>,
+    error: Option<String>,
+    referrer: Option<String>,
+    google_api_key: Option<String>,
+    one_fichier_keys: Option<Vec<String>>,
+    headers: Option<Vec<String>>,
+    version: Option<f32>,
+    client_cert_pub: Option<String>,
+    client_cert_key: Option<String>,
+    theme_black_list: Option<Vec<String>>,
+    theme_white_list: Option<Vec<String>>,
+    theme_error: Option<String>,
+    locations: Option<Vec<TinfoilLocation>>,
+}
+
+impl TinfoilIndexBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn files<F>(mut self, files: &[F]) -> Self
+    where
+        F: TinfoilFileLike,
+    {
+        self.files = Some(
+            files
+                .iter()
+                .map(|file| TinfoilFile {
+                    url: file.get_url(),
+                    size: file.get_size(),
+                })
+                .collect(),
+        );
+        self
+    }
+
+    pub fn directories(mut self, directories: impl Into<Option<Vec<String>>>) -> Self {
+        self.directories = directories.into();
+        self
+    }
+
+    pub fn success(mut self, success: impl Into<Option<String>>) -> Self {
+        self.success = success.into();
+        self
+    }
+
+    /// Message Tinfoil shows instead of loading the index
+    pub fn error(mut self, error: impl Into<Option<String>>) -> Self {
+        self.error = error.into();
+        self
+    }
+
+    pub fn referrer(mut self, referrer: impl Into<Option<String>>) -> Self {
+        self.referrer = referrer.into();
+        self
+    }
+
+    pub fn google_api_key(mut self, google_api_key: impl Into<Option<String>>) -> Self {
+        self.google_api_key = google_api_key.into();
+        self
+    }
+
+    pub fn one_fichier_keys(mut self, one_fichier_keys: impl Into<Option<Vec<String>>>) -> Self {
+        self.one_fichier_keys = one_fichier_keys.into();
+        self
+    }
+
+    pub fn headers(mut self, headers: impl Into<Option<Vec<String>>>) -> Self {
+        self.headers = headers.into();
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<Option<f32>>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn client_cert_pub(mut self, client_cert_pub: impl Into<Option<String>>) -> Self {
+        self.client_cert_pub = client_cert_pub.into();
+        self
+    }
+
+    pub fn client_cert_key(mut self, client_cert_key: impl Into<Option<String>>) -> Self {
+        self.client_cert_key = client_cert_key.into();
+        self
+    }
+
+    pub fn client_certificate(
+        self,
+        certificate: impl Into<Option<TinfoilClientCertificate>>,
+    ) -> Self {
+        match certificate.into() {
+            None => self.client_cert_pub(None).client_cert_key(None),
+            Some(certificate) => self
+                .client_cert_pub(certificate.public)
+                .client_cert_key(certificate.key),
+        }
+    }
+
+    pub fn theme_blacklist(mut self, theme_blacklist: impl Into<Option<Vec<String>>>) -> Self {
+        self.theme_black_list = theme_blacklist.into();
+        self
+    }
+
+    pub fn theme_whitelist(mut self, theme_whitelist: impl Into<Option<Vec<String>>>) -> Self {
+        self.theme_white_list = theme_whitelist.into();
+        self
+    }
+
+    pub fn theme_error(mut self, theme_error: impl Into<Option<String>>) -> Self {
+        self.theme_error = theme_error.into();
+        self
+    }
+
+    pub fn locations(mut self, locations: impl Into<Option<Vec<TinfoilLocation>>>) -> Self {
+        self.locations = locations.into();
+        self
+    }
+
+    pub fn build(self) -> Result<TinfoilIndex, TinfoilIndexBuilderError> {
+        lazy_static! {
+            static ref HEADER_REGEX: Regex =
+                Regex::new(r"^[!#$%&'*+\-.^_`|~0-9A-Za-z]+:\s*\S.*$").unwrap();
+        }
+
+        if let Some(files) = &self.files {
+            for (position, file) in files.iter().enumerate() {
+                if file.url.trim().is_empty() {
+                    return Err(TinfoilIndexBuilderError::EmptyFileUrl { position });
+                }
+
+                if file.size < 0 {
+                    return Err(TinfoilIndexBuilderError::NegativeFileSize {
+                        url: file.url.clone(),
+                        size: file.size,
+                    });
+                }
+            }
+        }
+
+        if let Some(directories) = &self.directories {
+            for (position, directory) in directories.iter().enumerate() {
+                if directory.trim().is_empty() {
+                    return Err(TinfoilIndexBuilderError::EmptyDirectoryUrl { position });
+                }
+            }
+        }
+
+        if let Some(headers) = &self.headers {
+            for header in headers {
+                if !HEADER_REGEX.is_match(header) {
+                    return Err(TinfoilIndexBuilderError::InvalidHeader {
+                        header: header.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(version) = self.version {
+            if !version.is_finite() || version <= 0.0 {
+                return Err(TinfoilIndexBuilderError::InvalidVersion { version });
+            }
+        }
+
+        if let Some(locations) = &self.locations {
+            for location in locations {
+                if let Err(e) = location.validate() {
+                    return Err(TinfoilIndexBuilderError::InvalidLocation {
+                        url: location.url.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        if self.client_cert_pub.is_some() != self.client_cert_key.is_some() {
+            return Err(TinfoilIndexBuilderError::IncompleteClientCertificate);
+        }
+
+        Ok(self.build_unchecked())
+    }
+
+    /// Builds the index as is, for callers which never validated their input
+    pub(crate) fn build_unchecked(self) -> TinfoilIndex {
+        let mut index = TinfoilIndex::new();
+
+        index.files = self.files;
+        index.directories = self.directories;
+        index.success = self.success;
+        index.error = self.error;
+        index.referrer = self.referrer;
+        index.google_api_key = self.google_api_key;
+        index.one_fichier_keys = self.one_fichier_keys;
+        index.headers = self.headers;
+        index.version = self.version;
+        index.client_cert_pub = self.client_cert_pub;
+        index.client_cert_key = self.client_cert_key;
+        index.theme_black_list = self.theme_black_list;
+        index.theme_white_list = self.theme_white_list;
+        index.theme_error = self.theme_error;
+        index.locations = self.locations;
+
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::model::HttpFile;
+
+    fn file(url: &str, size: i64) -> HttpFile {
+        HttpFile::new(
+            url.to_string(),
+            size,
+            "Game.nsp".to_string(),
+            "Game.nsp".to_string(),
+        )
+    }
+
+    fn location(url: &str, title: &str, action: &str) -> TinfoilLocation {
+        TinfoilLocation {
+            url: url.to_string(),
+            title: title.to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    #[test]
+    fn builds_valid_indexes() {
+        let index = TinfoilIndexBuilder::new()
+            .files(&[file("https://example.com/Game.nsp", 0)])
+            .directories(vec!["https://example.com/more/".to_string()])
+            .success("Welcome".to_string())
+            .error("Maintenance".to_string())
+            .headers(vec!["Authorization: Bearer token".to_string()])
+            .version(17.0)
+            .locations(vec![location("https://example.com/", "Games", "add")])
+            .client_cert_pub("public".to_string())
+            .client_cert_key("key".to_string())
+            .build()
+            .unwrap();
+
+        let files = index.files.unwrap();
+        assert_eq!(files[0].url, "https://example.com/Game.nsp");
+        assert_eq!(files[0].size, 0);
+        assert_eq!(index.success.as_deref(), Some("Welcome"));
+        assert_eq!(index.error.as_deref(), Some("Maintenance"));
+        assert_eq!(index.version, Some(17.0));
+        assert_eq!(index.locations.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn leaves_unset_fields_empty() {
+        let index = TinfoilIndexBuilder::new().build().unwrap();
+
+        assert!(index.files.is_none());
+        assert!(index.directories.is_none());
+        assert!(index.success.is_none());
+        assert!(index.headers.is_none());
+    }
+
+    #[test]
+    fn rejects_empty_urls_and_negative_sizes() {
+        let files = [file("https://example.com/Game.nsp", 1), file(" ", 1)];
+        assert_eq!(
+            TinfoilIndexBuilder::new()
+                .files(&files)
+                .build()
+                .unwrap_err(),
+            TinfoilIndexBuilderError::EmptyFileUrl { position: 1 }
+        );
+
+        let files = [file("https://example.com/Game.nsp", -1)];
+        assert_eq!(
+            TinfoilIndexBuilder::new()
+                .files(&files)
+                .build()
+                .unwrap_err(),
+            TinfoilIndexBuilderError::NegativeFileSize {
+                url: "https://example.com/Game.nsp".to_string(),
+                size: -1
+            }
+        );
+
+        assert_eq!(
+            TinfoilIndexBuilder::new()
+                .directories(vec!["".to_string()])
+                .build()
+                .unwrap_err(),
+            TinfoilIndexBuilderError::EmptyDirectoryUrl { position: 0 }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for header in [
+            "Authorization",
+            ": value",
+            "Name:",
+            "Na me: value",
+            "Name:   ",
+        ] {
+            assert_eq!(
+                TinfoilIndexBuilder::new()
+                    .headers(vec![header.to_string()])
+                    .build()
+                    .unwrap_err(),
+                TinfoilIndexBuilderError::InvalidHeader {
+                    header: header.to_string()
+                },
+                "{}",
+                header
+            );
+        }
+
+        assert!(TinfoilIndexBuilder::new()
+            .headers(vec!["X-Custom:value".to_string()])
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        for version in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                TinfoilIndexBuilder::new().version(version).build(),
+                Err(TinfoilIndexBuilderError::InvalidVersion { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_locations() {
+        let invalid = [
+            location("not a url", "Games", "add"),
+            location("https://", "Games", "add"),
+            location("https://example.com/", " ", "add"),
+            location("https://example.com/", "Games", "delete"),
+        ];
+
+        for location in invalid {
+            let url = location.url.clone();
+
+            match TinfoilIndexBuilder::new()
+                .locations(vec![location])
+                .build()
+                .unwrap_err()
+            {
+                TinfoilIndexBuilderError::InvalidLocation { url: invalid, .. } => {
+                    assert_eq!(invalid, url)
+                }
+                other => panic!("unexpected error {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn requires_both_halves_of_the_client_certificate() {
+        assert_eq!(
+            TinfoilIndexBuilder::new()
+                .client_cert_pub("public".to_string())
+                .build()
+                .unwrap_err(),
+            TinfoilIndexBuilderError::IncompleteClientCertificate
+        );
+        assert_eq!(
+            TinfoilIndexBuilder::new()
+                .client_cert_key("key".to_string())
+                .build()
+                .unwrap_err(),
+            TinfoilIndexBuilderError::IncompleteClientCertificate
+        );
+    }
+
+    #[test]
+    fn describes_errors() {
+        assert_eq!(
+            TinfoilIndexBuilderError::InvalidHeader {
+                header: "Name".to_string()
+            }
+            .to_string(),
+            "Header \"Name\" is not in the format \"Name: value\""
+        );
+        assert_eq!(
+            TinfoilIndexBuilderError::EmptyFileUrl { position: 3 }.to_string(),
+            "File at position 3 has an empty url"
+        );
+    }
+}
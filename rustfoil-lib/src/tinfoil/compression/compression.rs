@@ -1,6 +1,7 @@
+use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
-use std::io::Write;
+use std::io::{Read, Write};
 
 #[derive(Debug, Clone, Copy)]
 pub enum TinfoilCompression {
@@ -10,6 +11,15 @@ pub enum TinfoilCompression {
 }
 
 impl TinfoilCompression {
+    pub fn from_flag(flag: u8) -> anyhow::Result<Self> {
+        match flag & 0x0F {
+            0x00 => Ok(TinfoilCompression::Off),
+            0x0D => Ok(TinfoilCompression::ZSTD),
+            0x0E => Ok(TinfoilCompression::Zlib),
+            other => Err(anyhow::anyhow!("Unknown compression flag {:#04x}", other)),
+        }
+    }
+
     pub fn compress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
         match &self {
             TinfoilCompression::Off => Ok(data.to_vec()),
@@ -21,4 +31,17 @@ impl TinfoilCompression {
             }
         }
     }
+
+    pub fn decompress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match &self {
+            TinfoilCompression::Off => Ok(data.to_vec()),
+            TinfoilCompression::ZSTD => Ok(zstd::stream::decode_all(data)?),
+            TinfoilCompression::Zlib => {
+                let mut decoder = ZlibDecoder::new(data);
+                let mut decompressed = Vec::new();
+                decoder.read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use crate::abstraction::file::TinfoilFileLike;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct TinfoilFolder<F> {
+    /// Path of the folder relative to the scanned folder, empty for the scanned folder itself
+    pub path: String,
+    pub url: String,
+    pub files: Vec<F>,
+}
+
+impl<F> TinfoilFolder<F> {
+    /// Url of an index named `file_name` placed inside the folder
+    pub fn sub_index_url(&self, file_name: &str) -> String {
+        format!(
+            "{}/{}",
+            self.url.trim_end_matches('/'),
+            urlencoding::encode(file_name)
+        )
+    }
+
+    /// Path of the folder as local path, rejects segments like `..` which would leave the folder it's joined to
+    pub fn local_path(&self) -> anyhow::Result<PathBuf> {
+        let mut path = PathBuf::new();
+
+        for segment in self.path.split('/') {
+            let mut components = Path::new(segment).components();
+
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) => path.push(name),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Folder {} contains \"{}\" which can't be used as local folder name",
+                        self.url,
+                        segment
+                    ));
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// ID of the folder if it's a Google Drive folder
+    pub fn gdrive_folder_id(&self) -> Option<&str> {
+        let id = self.url.strip_prefix("gdrive:")?;
+
+        Some(id.split_once('#').map_or(id, |(id, _)| id))
+    }
+}
+
+/// Groups files by the folder containing them, keeping the order in which folders were first seen
+pub fn group_files_by_folder<F>(files: Vec<F>) -> Vec<TinfoilFolder<F>>
+where
+    F: TinfoilFileLike,
+{
+    let mut folders: Vec<TinfoilFolder<F>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for file in files {
+        let url = file.get_folder_url();
+
+        match positions.get(&url) {
+            Some(position) => folders[*position].files.push(file),
+            None => {
+                let path = file
+                    .get_path()
+                    .rsplit_once('/')
+                    .map(|(parent, _)| parent.to_string())
+                    .unwrap_or_default();
+
+                positions.insert(url.clone(), folders.len());
+                folders.push(TinfoilFolder {
+                    path,
+                    url,
+                    files: vec![file],
+                });
+            }
+        }
+    }
+
+    folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(path: &str) -> TinfoilFolder<()> {
+        TinfoilFolder {
+            path: path.to_string(),
+            url: format!("https://example.com/{}", path),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn maps_folder_paths_to_local_paths() {
+        assert_eq!(
+            folder("RPG Games/Updates").local_path().unwrap(),
+            Path::new("RPG Games").join("Updates")
+        );
+        assert_eq!(
+            folder("..hidden").local_path().unwrap(),
+            PathBuf::from("..hidden")
+        );
+    }
+
+    #[test]
+    fn rejects_folder_paths_leaving_the_output_folder() {
+        for path in [
+            "..",
+            "Games/..",
+            "Games/../..",
+            "/etc",
+            "Games//Updates",
+            "Games/.",
+            "",
+        ] {
+            assert!(folder(path).local_path().is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn builds_sub_index_urls() {
+        assert_eq!(
+            folder("RPG Games").sub_index_url("index.tfl"),
+            "https://example.com/RPG Games/index.tfl"
+        );
+        assert_eq!(
+            folder("Games/").sub_index_url("my index.tfl"),
+            "https://example.com/Games/my%20index.tfl"
+        );
+    }
+}
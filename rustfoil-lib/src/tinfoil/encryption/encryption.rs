@@ -2,17 +2,21 @@ use std::path::PathBuf;
 
 use aes::cipher::KeyInit;
 use aes::Aes128;
-use ecb::cipher::block_padding::ZeroPadding;
-use ecb::cipher::BlockEncryptMut;
-use ecb::Encryptor;
+use ecb::cipher::block_padding::{NoPadding, ZeroPadding};
+use ecb::cipher::{BlockDecryptMut, BlockEncryptMut};
+use ecb::{Decryptor, Encryptor};
 use rand::rngs::OsRng;
 use rand::RngCore;
-use rsa::pkcs8::DecodePublicKey;
-use rsa::RsaPublicKey;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{RsaPrivateKey, RsaPublicKey};
 use sha2::Sha256;
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, BufReader};
 
+/// Length of the wrapped session key in the index header, what an RSA-2048 key produces
+pub const SESSION_KEY_LENGTH: usize = 0x100;
+
 #[derive(Debug, Clone, Copy)]
 pub enum TinfoilEncryption {
     NoEncrypt = 0x00,
@@ -20,6 +24,14 @@ pub enum TinfoilEncryption {
 }
 
 impl TinfoilEncryption {
+    pub fn from_flag(flag: u8) -> anyhow::Result<Self> {
+        match flag & 0xF0 {
+            0x00 => Ok(TinfoilEncryption::NoEncrypt),
+            0xF0 => Ok(TinfoilEncryption::Encrypt),
+            other => Err(anyhow::anyhow!("Unknown encryption flag {:#04x}", other)),
+        }
+    }
+
     pub async fn encrypt(
         &self,
         data: Vec<u8>,
@@ -52,6 +64,249 @@ impl TinfoilEncryption {
             random_aes_key.as_ref(),
         )?;
 
+        if encrypted_aes_key.len() != SESSION_KEY_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Public Key {:?} is not an RSA-2048 key, Tinfoil only reads {} byte session keys but it produced {}",
+                file,
+                SESSION_KEY_LENGTH,
+                encrypted_aes_key.len()
+            ));
+        }
+
         Ok((encrypted_data, encrypted_aes_key))
     }
+
+    pub async fn decrypt(
+        &self,
+        data: &[u8],
+        session_key: &[u8],
+        data_length: usize,
+        file: &PathBuf,
+    ) -> anyhow::Result<Vec<u8>> {
+        // Inverse of encrypt, decrypt aes key with private key, decrypt data with aes key via ecb, strip zero padding using the recorded data length
+
+        let priv_key_file = File::open(file).await?;
+
+        let mut buf_reader = BufReader::new(priv_key_file);
+        let mut priv_key_str = String::new();
+
+        buf_reader.read_to_string(&mut priv_key_str).await?;
+
+        let private_key = match RsaPrivateKey::from_pkcs8_pem(priv_key_str.as_str()) {
+            Ok(key) => key,
+            Err(_) => RsaPrivateKey::from_pkcs1_pem(priv_key_str.as_str())?,
+        };
+
+        let aes_key = private_key.decrypt(rsa::Oaep::new::<Sha256>(), session_key)?;
+
+        if aes_key.len() != 16 {
+            return Err(anyhow::anyhow!(
+                "Decrypted session key has invalid length {}",
+                aes_key.len()
+            ));
+        }
+
+        let ecb = Decryptor::<Aes128>::new(aes_key.as_slice().into());
+
+        let mut decrypted_data = ecb
+            .decrypt_padded_vec_mut::<NoPadding>(data)
+            .map_err(|_| anyhow::anyhow!("Encrypted data is not a multiple of the block size"))?;
+
+        if decrypted_data.len() < data_length {
+            return Err(anyhow::anyhow!(
+                "Decrypted data is shorter than the declared length of {} bytes",
+                data_length
+            ));
+        }
+
+        decrypted_data.truncate(data_length);
+
+        Ok(decrypted_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1::EncodeRsaPrivateKey;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+    use std::sync::OnceLock;
+
+    struct Keys {
+        folder: PathBuf,
+        public: PathBuf,
+        pkcs8: PathBuf,
+        pkcs1: PathBuf,
+    }
+
+    /// Generating RSA-2048 keys is slow in debug builds, so the tests share two of them
+    fn private_key(index: usize) -> RsaPrivateKey {
+        static KEYS: OnceLock<Vec<RsaPrivateKey>> = OnceLock::new();
+
+        KEYS.get_or_init(|| {
+            (0..2)
+                .map(|_| RsaPrivateKey::new(&mut OsRng, 2048).unwrap())
+                .collect()
+        })[index]
+            .clone()
+    }
+
+    fn write_keys(name: &str, private_key: RsaPrivateKey) -> Keys {
+        let folder = std::env::temp_dir().join(format!(
+            "rustfoil-encryption-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&folder).unwrap();
+
+        let public_key = RsaPublicKey::from(&private_key);
+
+        let keys = Keys {
+            public: folder.join("public.pem"),
+            pkcs8: folder.join("pkcs8.pem"),
+            pkcs1: folder.join("pkcs1.pem"),
+            folder,
+        };
+
+        std::fs::write(
+            &keys.public,
+            public_key.to_public_key_pem(LineEnding::LF).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            &keys.pkcs8,
+            private_key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .unwrap();
+        std::fs::write(
+            &keys.pkcs1,
+            private_key.to_pkcs1_pem(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .unwrap();
+
+        keys
+    }
+
+    #[tokio::test]
+    async fn decrypts_with_pkcs8_and_pkcs1_keys() {
+        let keys = write_keys("round-trip", private_key(0));
+        let data = b"not a multiple of the block size".to_vec();
+
+        let (encrypted, session_key) = TinfoilEncryption::Encrypt
+            .encrypt(data.clone(), &keys.public)
+            .await
+            .unwrap();
+        assert_eq!(encrypted.len() % 16, 0);
+
+        for private_key in [&keys.pkcs8, &keys.pkcs1] {
+            let decrypted = TinfoilEncryption::Encrypt
+                .decrypt(&encrypted, &session_key, data.len(), private_key)
+                .await
+                .unwrap();
+            assert_eq!(decrypted, data);
+        }
+
+        std::fs::remove_dir_all(&keys.folder).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupt_input() {
+        let keys = write_keys("corrupt", private_key(0));
+        let data = vec![0x42u8; 40];
+
+        let (encrypted, session_key) = TinfoilEncryption::Encrypt
+            .encrypt(data.clone(), &keys.public)
+            .await
+            .unwrap();
+
+        let decrypt = |data: Vec<u8>, session_key: Vec<u8>, length: usize, key: PathBuf| async move {
+            TinfoilEncryption::Encrypt
+                .decrypt(&data, &session_key, length, &key)
+                .await
+        };
+
+        // truncated to a partial block
+        assert!(decrypt(
+            encrypted[..20].to_vec(),
+            session_key.clone(),
+            20,
+            keys.pkcs8.clone()
+        )
+        .await
+        .is_err());
+
+        // declares more data than there is
+        assert!(decrypt(
+            encrypted.clone(),
+            session_key.clone(),
+            49,
+            keys.pkcs8.clone()
+        )
+        .await
+        .is_err());
+
+        let mut corrupt_session_key = session_key.clone();
+        corrupt_session_key[0] ^= 0xFF;
+        assert!(decrypt(
+            encrypted.clone(),
+            corrupt_session_key,
+            40,
+            keys.pkcs8.clone()
+        )
+        .await
+        .is_err());
+
+        assert!(decrypt(
+            encrypted.clone(),
+            session_key[..64].to_vec(),
+            40,
+            keys.pkcs8.clone()
+        )
+        .await
+        .is_err());
+
+        // the public key isn't a private key
+        assert!(decrypt(
+            encrypted.clone(),
+            session_key.clone(),
+            40,
+            keys.public.clone()
+        )
+        .await
+        .is_err());
+
+        let other = write_keys("other", private_key(1));
+        assert!(decrypt(encrypted, session_key, 40, other.pkcs8.clone())
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(&keys.folder).unwrap();
+        std::fs::remove_dir_all(&other.folder).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_keys_other_than_rsa_2048() {
+        let keys = write_keys("small", RsaPrivateKey::new(&mut OsRng, 1024).unwrap());
+
+        let error = TinfoilEncryption::Encrypt
+            .encrypt(vec![0u8; 16], &keys.public)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("RSA-2048"), "{}", error);
+
+        std::fs::remove_dir_all(&keys.folder).unwrap();
+    }
+
+    #[test]
+    fn reads_encryption_flags() {
+        assert!(matches!(
+            TinfoilEncryption::from_flag(0x0D),
+            Ok(TinfoilEncryption::NoEncrypt)
+        ));
+        assert!(matches!(
+            TinfoilEncryption::from_flag(0xFE),
+            Ok(TinfoilEncryption::Encrypt)
+        ));
+        assert!(TinfoilEncryption::from_flag(0x80).is_err());
+    }
 }
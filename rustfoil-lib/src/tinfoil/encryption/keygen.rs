@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use rand::rngs::OsRng;
+use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use tokio::fs;
+
+use crate::tinfoil::encryption::encryption::SESSION_KEY_LENGTH;
+use tokio::io::AsyncWriteExt;
+
+/// Tinfoil expects RSA-2048 keys, the index header has room for exactly one 0x100 byte wrapped session key
+pub const KEY_BITS: usize = SESSION_KEY_LENGTH * 8;
+
+#[derive(Debug, Clone)]
+pub struct TinfoilKeyPair {
+    pub public_key_pem: String,
+    pub private_key_pem: String,
+}
+
+impl TinfoilKeyPair {
+    pub fn generate() -> anyhow::Result<Self> {
+        let mut rng = OsRng;
+
+        let private_key = RsaPrivateKey::new(&mut rng, KEY_BITS)?;
+        let public_key = RsaPublicKey::from(&private_key);
+
+        // Public key as SPKI PEM, the format RsaPublicKey::from_public_key_pem reads when encrypting
+        let public_key_pem = public_key.to_public_key_pem(LineEnding::LF)?;
+        let private_key_pem = private_key.to_pkcs8_pem(LineEnding::LF)?.to_string();
+
+        Ok(Self {
+            public_key_pem,
+            private_key_pem,
+        })
+    }
+
+    pub async fn write(
+        &self,
+        public_key_path: &PathBuf,
+        private_key_path: &PathBuf,
+        force: bool,
+    ) -> anyhow::Result<()> {
+        if !force {
+            for path in [public_key_path, private_key_path] {
+                if fs::try_exists(path).await? {
+                    return Err(anyhow::anyhow!(
+                        "{:?} already exists, refusing to overwrite it",
+                        path
+                    ));
+                }
+            }
+        }
+
+        fs::write(public_key_path, &self.public_key_pem).await?;
+        write_private_key(private_key_path, &self.private_key_pem, force).await?;
+
+        Ok(())
+    }
+}
+
+/// Writes a private key that is only readable by its owner from the moment it is created.
+pub(crate) async fn write_private_key(
+    path: &Path,
+    content: &str,
+    force: bool,
+) -> anyhow::Result<()> {
+    if force {
+        match fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options
+        .open(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create {:?}: {}", path, e))?;
+
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tinfoil::compression::compression::TinfoilCompression;
+    use crate::tinfoil::encryption::encryption::TinfoilEncryption;
+    use crate::tinfoil::model::TinfoilIndex;
+    use crate::tinfoil::service::TinfoilService;
+
+    #[tokio::test]
+    async fn generated_keys_round_trip_an_encrypted_index() {
+        let folder = std::env::temp_dir().join(format!("rustfoil-keygen-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+
+        let public_key_path = folder.join("public.pem");
+        let private_key_path = folder.join("private.pem");
+
+        let key_pair = TinfoilKeyPair::generate().unwrap();
+        key_pair
+            .write(&public_key_path, &private_key_path, true)
+            .await
+            .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&private_key_path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(key_pair
+            .write(&public_key_path, &private_key_path, false)
+            .await
+            .is_err());
+
+        let mut index = TinfoilIndex::new();
+        index.success = Some("Encrypted".to_string());
+
+        let service = TinfoilService::new();
+        let bytes = service
+            .generate_index_file(
+                index,
+                TinfoilCompression::ZSTD,
+                TinfoilEncryption::Encrypt,
+                Some(public_key_path),
+            )
+            .await
+            .unwrap();
+
+        let read = service
+            .read_index_file(&bytes, Some(private_key_path))
+            .await
+            .unwrap();
+
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(read.success.as_deref(), Some("Encrypted"));
+    }
+}
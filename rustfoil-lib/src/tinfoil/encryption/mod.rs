@@ -1 +1,2 @@
 pub mod encryption;
+pub mod keygen;
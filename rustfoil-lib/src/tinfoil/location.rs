@@ -0,0 +1,56 @@
+use crate::tinfoil::model::TinfoilLocation;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tokio::fs;
+use url::Url;
+
+pub static LOCATION_ACTIONS: [&str; 2] = ["add", "disable"];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LocationFile {
+    List(Vec<TinfoilLocation>),
+    Index { locations: Vec<TinfoilLocation> },
+}
+
+/// Reads locations from a JSON file, either a plain array or an object with a `locations` key
+pub async fn read_locations(path: &PathBuf) -> anyhow::Result<Vec<TinfoilLocation>> {
+    let raw = fs::read(path).await?;
+
+    let locations = match serde_json::from_slice(raw.as_slice())? {
+        LocationFile::List(locations) => locations,
+        LocationFile::Index { locations } => locations,
+    };
+
+    for location in &locations {
+        location.validate()?;
+    }
+
+    Ok(locations)
+}
+
+impl TinfoilLocation {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !LOCATION_ACTIONS.contains(&self.action.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Location {} has unknown action \"{}\", expected one of {:?}",
+                self.url,
+                self.action,
+                LOCATION_ACTIONS
+            ));
+        }
+
+        let url = Url::parse(&self.url)
+            .map_err(|e| anyhow::anyhow!("Location {} has a malformed url: {}", self.url, e))?;
+
+        if matches!(url.scheme(), "http" | "https") && url.host_str().is_none() {
+            return Err(anyhow::anyhow!("Location {} has no host", self.url));
+        }
+
+        if self.title.trim().is_empty() {
+            return Err(anyhow::anyhow!("Location {} has an empty title", self.url));
+        }
+
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 pub mod auth;
+pub mod builder;
+pub mod certificate;
 pub mod compression;
+pub mod directory;
 pub mod encoding;
 pub mod encryption;
+pub mod location;
 pub mod model;
 pub mod service;
+pub mod user;
@@ -1,3 +1,5 @@
+use crate::tinfoil::compression::compression::TinfoilCompression;
+use crate::tinfoil::encryption::encryption::TinfoilEncryption;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
@@ -8,6 +10,7 @@ pub struct TinfoilIndex {
     pub files: Option<Vec<TinfoilFile>>,
     pub directories: Option<Vec<String>>,
     pub success: Option<String>,
+    pub error: Option<String>,
     pub referrer: Option<String>,
     pub google_api_key: Option<String>,
     pub one_fichier_keys: Option<Vec<String>>,
@@ -34,6 +37,14 @@ pub struct TinfoilLocation {
     pub action: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct TinfoilIndexHeader {
+    pub compression: TinfoilCompression,
+    pub encryption: TinfoilEncryption,
+    pub session_key: Vec<u8>,
+    pub data_length: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TinfoilToken {
     pub access_token: String,
@@ -46,6 +57,7 @@ impl TinfoilIndex {
             files: None,
             directories: None,
             success: None,
+            error: None,
             referrer: None,
             google_api_key: None,
             one_fichier_keys: None,
@@ -2,9 +2,13 @@ use std::io::Write;
 use std::path::PathBuf;
 
 use crate::abstraction::file::TinfoilFileLike;
+use crate::tinfoil::builder::TinfoilIndexBuilder;
 use crate::tinfoil::compression::compression::TinfoilCompression;
-use crate::tinfoil::encryption::encryption::TinfoilEncryption;
-use crate::tinfoil::model::{TinfoilFile, TinfoilIndex};
+use crate::tinfoil::encryption::encryption::{TinfoilEncryption, SESSION_KEY_LENGTH};
+use crate::tinfoil::model::{TinfoilIndex, TinfoilIndexHeader};
+
+const MAGIC: &[u8; 7] = b"TINFOIL";
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + SESSION_KEY_LENGTH + 8;
 
 pub struct TinfoilService {}
 
@@ -13,6 +17,7 @@ impl TinfoilService {
         Self {}
     }
 
+    /// Builds the index without validation, prefer [TinfoilIndexBuilder]
     pub fn generate_index<F>(
         &self,
         files: Option<&Vec<F>>,
@@ -30,33 +35,24 @@ impl TinfoilService {
     where
         F: TinfoilFileLike,
     {
-        let mut index = TinfoilIndex::new();
+        let mut builder = TinfoilIndexBuilder::new();
 
         if let Some(files) = files {
-            index.files = Some(Vec::new());
-
-            for file in files {
-                let tinfoil_file = TinfoilFile {
-                    url: file.get_url(),
-                    size: file.get_size(),
-                };
-
-                index.add_file(tinfoil_file);
-            }
+            builder = builder.files(files);
         }
 
-        index.directories = directories;
-        index.success = success;
-        index.referrer = referrer;
-        index.google_api_key = google_api_key;
-        index.one_fichier_keys = oneficher_api_keys;
-        index.headers = headers;
-        index.version = min_version;
-        index.theme_black_list = theme_blacklist;
-        index.theme_white_list = theme_whitelist;
-        index.theme_error = theme_error;
-
-        Ok(index)
+        Ok(builder
+            .directories(directories)
+            .success(success)
+            .referrer(referrer)
+            .google_api_key(google_api_key)
+            .one_fichier_keys(oneficher_api_keys)
+            .headers(headers)
+            .version(min_version)
+            .theme_blacklist(theme_blacklist)
+            .theme_whitelist(theme_whitelist)
+            .theme_error(theme_error)
+            .build_unchecked())
     }
 
     pub async fn generate_index_file(
@@ -72,7 +68,7 @@ impl TinfoilService {
         let data_length = data.len();
 
         let session_key = match encryption {
-            TinfoilEncryption::NoEncrypt => b"\x00".repeat(0x100),
+            TinfoilEncryption::NoEncrypt => b"\x00".repeat(SESSION_KEY_LENGTH),
             TinfoilEncryption::Encrypt => {
                 let encryption_key_path = match encryption_key {
                     None => {
@@ -93,7 +89,7 @@ impl TinfoilService {
 
         let flag = (compression as u8) | (encryption as u8);
 
-        bytes.write(b"TINFOIL")?;
+        bytes.write(MAGIC)?;
         bytes.write(&flag.to_le_bytes())?;
         bytes.write(&session_key)?;
         bytes.write(&data_length.to_le_bytes())?;
@@ -101,4 +97,209 @@ impl TinfoilService {
 
         Ok(bytes)
     }
+
+    pub fn read_index_header(&self, bytes: &[u8]) -> anyhow::Result<TinfoilIndexHeader> {
+        if bytes.len() < HEADER_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Index file is too short ({} bytes) to contain a header",
+                bytes.len()
+            ));
+        }
+
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return Err(anyhow::anyhow!("Index file does not start with TINFOIL magic"));
+        }
+
+        let flag = bytes[MAGIC.len()];
+
+        let session_key_start = MAGIC.len() + 1;
+        let session_key = bytes[session_key_start..session_key_start + SESSION_KEY_LENGTH].to_vec();
+
+        let mut data_length = [0u8; 8];
+        data_length.copy_from_slice(&bytes[HEADER_LENGTH - 8..HEADER_LENGTH]);
+
+        Ok(TinfoilIndexHeader {
+            compression: TinfoilCompression::from_flag(flag)?,
+            encryption: TinfoilEncryption::from_flag(flag)?,
+            session_key,
+            data_length: u64::from_le_bytes(data_length) as usize,
+        })
+    }
+
+    pub async fn read_index_file(
+        &self,
+        bytes: &[u8],
+        decryption_key: Option<PathBuf>,
+    ) -> anyhow::Result<TinfoilIndex> {
+        let header = self.read_index_header(bytes)?;
+        let data = &bytes[HEADER_LENGTH..];
+
+        let data = match header.encryption {
+            TinfoilEncryption::NoEncrypt => {
+                if data.len() != header.data_length {
+                    return Err(anyhow::anyhow!(
+                        "Index file declares {} bytes of data but contains {}",
+                        header.data_length,
+                        data.len()
+                    ));
+                }
+
+                data.to_vec()
+            }
+            TinfoilEncryption::Encrypt => {
+                let decryption_key_path = match decryption_key {
+                    None => {
+                        return Err(anyhow::anyhow!(
+                            "Index file is encrypted but no Decryption Key was provided"
+                        ));
+                    }
+                    Some(key_path) => key_path,
+                };
+
+                header
+                    .encryption
+                    .decrypt(
+                        data,
+                        &header.session_key,
+                        header.data_length,
+                        &decryption_key_path,
+                    )
+                    .await?
+            }
+        };
+
+        let data = header.compression.decompress(data.as_slice())?;
+
+        Ok(serde_json::from_slice(data.as_slice())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::model::HttpFile;
+
+    fn index() -> TinfoilIndex {
+        let mut index = TinfoilIndex::new();
+        index.success = Some("Hello".to_string());
+        index.directories = Some(vec!["https://example.com/".to_string()]);
+        index
+    }
+
+    async fn index_file(compression: TinfoilCompression) -> Vec<u8> {
+        TinfoilService::new()
+            .generate_index_file(index(), compression, TinfoilEncryption::NoEncrypt, None)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_back_generated_indexes() {
+        let service = TinfoilService::new();
+
+        for compression in [
+            TinfoilCompression::Off,
+            TinfoilCompression::ZSTD,
+            TinfoilCompression::Zlib,
+        ] {
+            let bytes = index_file(compression).await;
+
+            let header = service.read_index_header(&bytes).unwrap();
+            assert_eq!(header.compression as u8, compression as u8);
+            assert_eq!(header.encryption as u8, TinfoilEncryption::NoEncrypt as u8);
+            assert_eq!(header.session_key, vec![0u8; SESSION_KEY_LENGTH]);
+            assert_eq!(header.data_length, bytes.len() - HEADER_LENGTH);
+
+            let read = service.read_index_file(&bytes, None).await.unwrap();
+            assert_eq!(read.success.as_deref(), Some("Hello"));
+            assert_eq!(read.directories, index().directories);
+        }
+    }
+
+    #[test]
+    fn rejects_short_headers() {
+        let service = TinfoilService::new();
+
+        assert!(service.read_index_header(&[]).is_err());
+        assert!(service.read_index_header(b"TINFOIL").is_err());
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.resize(HEADER_LENGTH - 1, 0);
+        assert!(service.read_index_header(&bytes).is_err());
+
+        bytes.push(0);
+        assert!(service.read_index_header(&bytes).is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_magic_and_unknown_flags() {
+        let service = TinfoilService::new();
+        let bytes = index_file(TinfoilCompression::Off).await;
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b't';
+        assert!(service.read_index_header(&wrong_magic).is_err());
+
+        for flag in [0x05, 0x50] {
+            let mut unknown_flag = bytes.clone();
+            unknown_flag[MAGIC.len()] = flag;
+            assert!(service.read_index_header(&unknown_flag).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_and_corrupt_data() {
+        let service = TinfoilService::new();
+
+        let bytes = index_file(TinfoilCompression::Off).await;
+        assert!(service
+            .read_index_file(&bytes[..bytes.len() - 1], None)
+            .await
+            .is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(service.read_index_file(&trailing, None).await.is_err());
+
+        let mut corrupt = index_file(TinfoilCompression::ZSTD).await;
+        corrupt[HEADER_LENGTH] ^= 0xFF;
+        assert!(service.read_index_file(&corrupt, None).await.is_err());
+
+        let mut not_json = bytes.clone();
+        not_json[HEADER_LENGTH] = b'[';
+        assert!(service.read_index_file(&not_json, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn requires_a_key_for_encrypted_indexes() {
+        let service = TinfoilService::new();
+
+        let mut bytes = index_file(TinfoilCompression::Off).await;
+        bytes[MAGIC.len()] |= TinfoilEncryption::Encrypt as u8;
+
+        let error = service.read_index_file(&bytes, None).await.unwrap_err();
+        assert!(error.to_string().contains("no Decryption Key"), "{}", error);
+    }
+
+    #[test]
+    fn generate_index_does_not_validate() {
+        let index = TinfoilService::new()
+            .generate_index::<HttpFile>(
+                None,
+                Some(vec!["".to_string()]),
+                None,
+                None,
+                None,
+                None,
+                Some(vec!["not a header".to_string()]),
+                Some(-1.0),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(index.headers, Some(vec!["not a header".to_string()]));
+        assert_eq!(index.version, Some(-1.0));
+    }
 }
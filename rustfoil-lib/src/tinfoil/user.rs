@@ -0,0 +1,197 @@
+use crate::filter::title::TitleIdList;
+use argon2::password_hash::{PasswordHash, PasswordVerifier};
+use argon2::Argon2;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+
+/// A user from a TOML users file, the optional index fields override the ones of the command for this user
+#[derive(Debug, Clone, Deserialize)]
+pub struct TinfoilUser {
+    pub username: String,
+    /// Plain text or an argon2 (`$argon2id$...`) or bcrypt (`$2b$...`) hash
+    pub password: String,
+    /// Only allows the console with this `UID` header
+    pub uid: Option<String>,
+    /// Only allows requests with this `HAUTH` header, Tinfoil derives it from the host of the location
+    pub hauth: Option<String>,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    #[serde(default, deserialize_with = "deserialize_expiry")]
+    pub expires: Option<DateTime<Utc>>,
+    pub success: Option<String>,
+    /// Only these Title IDs end up in the index of the user
+    #[serde(default, deserialize_with = "deserialize_title_ids")]
+    pub title_allowlist: Option<TitleIdList>,
+    pub headers: Option<Vec<String>>,
+    /// Encrypts the index of the user with this key, even if the command doesn't encrypt
+    pub public_key: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TinfoilUsers {
+    #[serde(default, rename = "user")]
+    pub users: Vec<TinfoilUser>,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+impl TinfoilUsers {
+    pub async fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = tokio::fs::read_to_string(path).await?;
+
+        Self::parse(content.as_str())
+            .map_err(|e| anyhow::anyhow!("Failed to read users file {:?}: {}", path, e))
+    }
+
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let users: TinfoilUsers = toml::from_str(content)?;
+
+        for (position, user) in users.users.iter().enumerate() {
+            if user.username.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "User at position {} has an empty username",
+                    position
+                ));
+            }
+
+            // the username is used as file name of the index
+            if user
+                .username
+                .contains(|c: char| c == '/' || c == '\\' || c.is_control())
+                || user.username.starts_with('.')
+            {
+                return Err(anyhow::anyhow!(
+                    "Username \"{}\" can't be used as file name",
+                    user.username
+                ));
+            }
+
+            if users.users[..position]
+                .iter()
+                .any(|other| other.username == user.username)
+            {
+                return Err(anyhow::anyhow!("User {} is defined twice", user.username));
+            }
+
+            if let Err(e) = check_password_hash(user.password.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Password of user {} is not a valid hash: {}",
+                    user.username,
+                    e
+                ));
+            }
+        }
+
+        Ok(users)
+    }
+
+    /// Compares against every username in constant time, so the time taken doesn't reveal which ones exist
+    pub fn find(&self, username: &str) -> Option<&TinfoilUser> {
+        let mut found = None;
+
+        for user in &self.users {
+            if bool::from(user.username.as_bytes().ct_eq(username.as_bytes())) {
+                found = Some(user);
+            }
+        }
+
+        found
+    }
+}
+
+impl TinfoilUser {
+    /// Enabled & not expired
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.expires.is_none_or(|expires| expires > Utc::now())
+    }
+
+    /// Verifies hashed passwords, plain ones are compared in constant time
+    pub fn verify_password(&self, password: &str) -> bool {
+        let stored = self.password.as_str();
+
+        if stored.starts_with("$argon2") {
+            return PasswordHash::new(stored).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            });
+        }
+
+        if is_bcrypt_hash(stored) {
+            return bcrypt::verify(password, stored).unwrap_or(false);
+        }
+
+        stored.as_bytes().ct_eq(password.as_bytes()).into()
+    }
+
+    /// If the user gets an index of its own instead of the one of the command
+    pub fn has_own_index(&self) -> bool {
+        self.success.is_some()
+            || self.title_allowlist.is_some()
+            || self.headers.is_some()
+            || self.public_key.is_some()
+    }
+}
+
+fn is_bcrypt_hash(password: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| password.starts_with(prefix))
+}
+
+/// Passwords that look like a hash have to be one, a typo would otherwise turn it into a plain password
+fn check_password_hash(password: &str) -> anyhow::Result<()> {
+    if password.starts_with("$argon2") {
+        PasswordHash::new(password).map_err(|e| anyhow::anyhow!("{}", e))?;
+    } else if is_bcrypt_hash(password) {
+        bcrypt::HashParts::from_str(password)?;
+    }
+
+    Ok(())
+}
+
+fn deserialize_title_ids<'de, D>(deserializer: D) -> Result<Option<TitleIdList>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Vec<String>>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(entries) => TitleIdList::from_entries(entries)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Accepts TOML datetimes & dates, values without an offset are treated as UTC
+fn deserialize_expiry<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = match Option::<toml::value::Datetime>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(value) => value.to_string(),
+    };
+
+    if let Ok(expires) = DateTime::parse_from_rfc3339(value.as_str()) {
+        return Ok(Some(expires.with_timezone(&Utc)));
+    }
+
+    if let Ok(expires) = NaiveDateTime::parse_from_str(value.as_str(), "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(Some(expires.and_utc()));
+    }
+
+    match NaiveDate::parse_from_str(value.as_str(), "%Y-%m-%d") {
+        Ok(expires) => Ok(expires
+            .and_hms_opt(0, 0, 0)
+            .map(|expires| expires.and_utc())),
+        Err(_) => Err(serde::de::Error::custom(format!(
+            "expires \"{}\" is not a date or datetime",
+            value
+        ))),
+    }
+}
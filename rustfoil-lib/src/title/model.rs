@@ -0,0 +1,48 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TitleType {
+    Base,
+    Update,
+    Dlc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleInfo {
+    pub title_id: String,
+    pub base_title_id: String,
+    pub title_type: TitleType,
+    pub version: Option<u32>,
+    pub regions: Vec<String>,
+    pub languages: Vec<String>,
+}
+
+impl TitleType {
+    /// Classifies a Title ID by its last three hex digits, `000` is a base game, `800` an update & anything else a DLC
+    pub fn from_title_id(title_id: &str) -> TitleType {
+        let suffix: String = title_id
+            .chars()
+            .skip(title_id.len().saturating_sub(3))
+            .collect();
+
+        match suffix.as_str() {
+            "000" => TitleType::Base,
+            "800" => TitleType::Update,
+            _ => TitleType::Dlc,
+        }
+    }
+}
+
+impl TitleInfo {
+    pub fn new(title_id: &str) -> Option<TitleInfo> {
+        let id = u64::from_str_radix(title_id, 16).ok()?;
+        let title_id = format!("{:016X}", id);
+
+        Some(TitleInfo {
+            base_title_id: format!("{:016X}", id & !0x1FFF),
+            title_type: TitleType::from_title_id(&title_id),
+            title_id,
+            version: None,
+            regions: Vec::new(),
+            languages: Vec::new(),
+        })
+    }
+}
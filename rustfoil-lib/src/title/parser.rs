@@ -0,0 +1,154 @@
+use crate::title::model::TitleInfo;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub static REGIONS: [(&str, &str); 16] = [
+    ("US", "US"),
+    ("USA", "US"),
+    ("EU", "EU"),
+    ("EUR", "EU"),
+    ("UK", "UK"),
+    ("JP", "JP"),
+    ("JPN", "JP"),
+    ("KR", "KR"),
+    ("KOR", "KR"),
+    ("CN", "CN"),
+    ("CHN", "CN"),
+    ("TW", "TW"),
+    ("HK", "HK"),
+    ("ASIA", "ASIA"),
+    ("AU", "AU"),
+    ("WORLD", "WORLD"),
+];
+
+pub static LANGUAGES: [&str; 16] = [
+    "EN", "JA", "FR", "DE", "ES", "IT", "NL", "PT", "RU", "KO", "ZH", "ZHHANS", "ZHHANT", "PL",
+    "SV", "MULTI",
+];
+
+/// Parses Title ID, version, regions & languages from a file name like `Game [0100ABCD00010000][v65536][US](En,Fr).nsp`
+pub fn parse_title_info(file_name: &str) -> Option<TitleInfo> {
+    lazy_static! {
+        static ref TITLE_ID_REGEX: Regex =
+            Regex::new("(?:^|[^0-9A-Fa-f])([0-9A-Fa-f]{16})(?:[^0-9A-Fa-f]|$)").unwrap();
+        static ref VERSION_REGEX: Regex = Regex::new(r"(?i)\[v(\d+)\]").unwrap();
+        static ref TAG_REGEX: Regex = Regex::new(r"[\[(]([^\[\]()]+)[\])]").unwrap();
+    }
+
+    let title_id = TITLE_ID_REGEX.captures(file_name)?.get(1)?.as_str();
+
+    let mut info = TitleInfo::new(title_id)?;
+
+    info.version = VERSION_REGEX
+        .captures(file_name)
+        .and_then(|captures| captures.get(1))
+        .and_then(|version| version.as_str().parse().ok());
+
+    for tag in TAG_REGEX.captures_iter(file_name) {
+        let parts: Vec<String> = tag[1]
+            .split([',', '+', ' '])
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_uppercase())
+            .collect();
+
+        if parts.is_empty() {
+            continue;
+        }
+
+        let regions: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| {
+                REGIONS
+                    .iter()
+                    .find(|(alias, _)| alias == part)
+                    .map(|(_, region)| *region)
+            })
+            .collect();
+
+        if regions.len() == parts.len() {
+            for region in regions {
+                if !info.regions.iter().any(|known| known == region) {
+                    info.regions.push(region.to_string());
+                }
+            }
+            continue;
+        }
+
+        if parts.iter().all(|part| LANGUAGES.contains(&part.as_str())) {
+            for language in parts {
+                if !info.languages.contains(&language) {
+                    info.languages.push(language);
+                }
+            }
+        }
+    }
+
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::model::TitleType;
+
+    #[test]
+    fn parses_a_full_file_name() {
+        let info = parse_title_info("Game [0100abcd00010000][v65536][US](En,Fr).nsp").unwrap();
+
+        assert_eq!(info.title_id, "0100ABCD00010000");
+        assert_eq!(info.base_title_id, "0100ABCD00010000");
+        assert_eq!(info.title_type, TitleType::Base);
+        assert_eq!(info.version, Some(65536));
+        assert_eq!(info.regions, vec!["US"]);
+        assert_eq!(info.languages, vec!["EN", "FR"]);
+    }
+
+    #[test]
+    fn classifies_updates_and_dlcs() {
+        let update = parse_title_info("Game [0100ABCD00010800][v131072].nsp").unwrap();
+        assert_eq!(update.title_type, TitleType::Update);
+        assert_eq!(update.base_title_id, "0100ABCD00010000");
+
+        let dlc = parse_title_info("Game DLC [0100ABCD00011001].nsp").unwrap();
+        assert_eq!(dlc.title_type, TitleType::Dlc);
+        assert_eq!(dlc.base_title_id, "0100ABCD00010000");
+    }
+
+    #[test]
+    fn normalizes_regions_and_languages() {
+        let info =
+            parse_title_info("Game [0100ABCD00010000] (USA+EUR) [JPN] (en fr) (EN).xci").unwrap();
+
+        assert_eq!(info.regions, vec!["US", "EU", "JP"]);
+        assert_eq!(info.languages, vec!["EN", "FR"]);
+        assert_eq!(info.version, None);
+    }
+
+    #[test]
+    fn ignores_unknown_and_mixed_tags() {
+        let info =
+            parse_title_info("Game [0100ABCD00010000] (Rev 1) [US, Demo] (En,Klingon) [].nsp")
+                .unwrap();
+
+        assert!(info.regions.is_empty());
+        assert!(info.languages.is_empty());
+    }
+
+    #[test]
+    fn requires_exactly_sixteen_hex_digits() {
+        assert!(parse_title_info("Game.nsp").is_none());
+        assert!(parse_title_info("Game [0100ABCD0001000].nsp").is_none());
+        assert!(parse_title_info("Game [0100ABCD000100000].nsp").is_none());
+        assert!(parse_title_info("Game [0100ABCD0001000G].nsp").is_none());
+
+        let bare = parse_title_info("0100ABCD00010000.nsp").unwrap();
+        assert_eq!(bare.title_id, "0100ABCD00010000");
+    }
+
+    #[test]
+    fn ignores_versions_that_overflow() {
+        let info = parse_title_info("Game [0100ABCD00010000][v99999999999].nsp").unwrap();
+
+        assert_eq!(info.version, None);
+    }
+}